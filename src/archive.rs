@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Resolves a local path or `file://` URL to a path.
+pub fn local_path(location: &str) -> Result<PathBuf> {
+    if let Some(path) = location.strip_prefix("file://") {
+        // file://localhost/srv/x and file:///srv/x name the same file
+        let path = path.strip_prefix("localhost").unwrap_or(path);
+        return Ok(PathBuf::from(percent_decode(path)));
+    }
+    if location.contains("://") {
+        anyhow::bail!("unsupported location '{}': use a local path or a file:// URL", location);
+    }
+    Ok(PathBuf::from(location))
+}
+
+/// Extracts a `.tar.gz`/`.tgz`/`.tar` or `.zip` archive into `dest`, replacing whatever
+/// was there only once extraction has succeeded.
+pub fn extract(archive: &Path, dest: &Path) -> Result<()> {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let staging = dest.with_file_name(format!(
+        ".{}.extracting",
+        dest.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+    ));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+    let result = unpack(archive, &name, &staging);
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    result?;
+
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)?;
+    }
+    std::fs::rename(&staging, dest)?;
+    Ok(())
+}
+
+fn unpack(archive: &Path, name: &str, staging: &Path) -> Result<()> {
+    let file = File::open(archive).with_context(|| format!("opening {:?}", archive))?;
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(staging)?;
+    } else if name.ends_with(".tar") {
+        tar::Archive::new(file).unpack(staging)?;
+    } else if name.ends_with(".zip") {
+        zip::ZipArchive::new(file)?.extract(staging)?;
+    } else {
+        anyhow::bail!("unsupported archive {:?}: expected .tar.gz, .tgz, .tar or .zip", archive);
+    }
+    Ok(())
+}
+
+/// Returns the root of an extracted archive's contents. Release tarballs usually wrap
+/// everything in one versioned top-level directory, which is skipped when `skills_path`
+/// isn't found at the top. With an empty `skills_path` the skills sit at the top
+/// themselves, so a lone directory is skipped unless it is a skill.
+pub fn content_root(dir: &Path, skills_path: &str) -> Result<PathBuf> {
+    if !skills_path.is_empty() && dir.join(skills_path).exists() {
+        return Ok(dir.to_path_buf());
+    }
+    let entries: Vec<_> = std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    match entries.as_slice() {
+        [only] if only.path().is_dir() && !only.path().join("SKILL.md").exists() => Ok(only.path()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(local_path("/srv/skills.zip").unwrap(), PathBuf::from("/srv/skills.zip"));
+        assert_eq!(
+            local_path("file:///srv/my%20skills.tar.gz").unwrap(),
+            PathBuf::from("/srv/my skills.tar.gz")
+        );
+        assert_eq!(local_path("file://localhost/srv/x").unwrap(), PathBuf::from("/srv/x"));
+        assert!(local_path("https://example.com/skills.zip").is_err());
+    }
+
+    #[test]
+    fn test_extract_tarball_and_zip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let tarball = dir.path().join("skills-1.0.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tarball).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let content = b"# PDF";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "skills-1.0/skills/pdf/SKILL.md", &content[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let dest = dir.path().join("extracted");
+        extract(&tarball, &dest).unwrap();
+        let root = content_root(&dest, "skills").unwrap();
+        assert_eq!(root, dest.join("skills-1.0"));
+        assert!(root.join("skills/pdf/SKILL.md").exists());
+
+        let zipfile = dir.path().join("skills.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zipfile).unwrap());
+        writer
+            .start_file("pdf/SKILL.md", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(content).unwrap();
+        writer.finish().unwrap();
+
+        // Re-extracting replaces the previous contents
+        extract(&zipfile, &dest).unwrap();
+        assert!(!dest.join("skills-1.0").exists());
+        assert_eq!(content_root(&dest, "").unwrap(), dest);
+        assert!(dest.join("pdf/SKILL.md").exists());
+
+        // A wrapped tarball with the skills at the top of the wrapper
+        let wrapped = dir.path().join("skills-2.0.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&wrapped).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "skills-2.0/pdf/SKILL.md", &content[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        extract(&wrapped, &dest).unwrap();
+        let root = content_root(&dest, "").unwrap();
+        assert_eq!(root, dest.join("skills-2.0"));
+        assert!(root.join("pdf/SKILL.md").exists());
+
+        let rar = dir.path().join("skills.rar");
+        std::fs::write(&rar, b"Rar!").unwrap();
+        assert!(extract(&rar, &dest).is_err());
+        assert!(root.join("pdf/SKILL.md").exists());
+    }
+}
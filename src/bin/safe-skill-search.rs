@@ -1,23 +1,78 @@
+mod archive {
+    include!("../archive.rs");
+}
+mod config {
+    include!("../config.rs");
+}
 mod db {
     include!("../db.rs");
 }
+mod dedup {
+    include!("../dedup.rs");
+}
+mod feed {
+    include!("../feed.rs");
+}
+mod forge {
+    include!("../forge.rs");
+}
+mod git {
+    include!("../git.rs");
+}
 mod github {
     include!("../github.rs");
 }
+mod http {
+    include!("../http.rs");
+}
 mod index {
     include!("../index.rs");
 }
+mod local {
+    include!("../local.rs");
+}
+mod lock {
+    include!("../lock.rs");
+}
+mod metrics {
+    include!("../metrics.rs");
+}
+mod plugin {
+    include!("../plugin.rs");
+}
 mod quality {
     include!("../quality.rs");
 }
+mod report {
+    include!("../report.rs");
+}
+mod semantic {
+    include!("../semantic.rs");
+}
 mod skillssh {
     include!("../skillssh.rs");
 }
+mod snapshot {
+    include!("../snapshot.rs");
+}
+mod source {
+    include!("../source.rs");
+}
+mod sync {
+    include!("../sync.rs");
+}
+mod typosquat {
+    include!("../typosquat.rs");
+}
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use metrics::MetricKind;
 use quality::QualityScores;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
@@ -35,15 +90,38 @@ struct Cli {
     /// Verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Sync registries whose data is older than their max_age before answering
+    #[arg(long, global = true)]
+    auto_sync: bool,
+
+    /// Never touch the network: use local data, a catalog snapshot, and local registries only
+    #[arg(long, global = true, conflicts_with = "auto_sync")]
+    offline: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Sync skills from all registries
     Sync {
+        #[command(subcommand)]
+        command: Option<SyncCommands>,
+
         /// Force full resync (ignore cache)
         #[arg(long)]
         force: bool,
+
+        /// Maximum number of registries and source repos fetched concurrently
+        #[arg(short, long, default_value_t = sync::DEFAULT_CONCURRENCY)]
+        jobs: usize,
+
+        /// Pin a registry to a branch, tag or commit for this sync (repeatable), e.g. anthropic=v1.2.0
+        #[arg(long = "ref", value_name = "REGISTRY=REF")]
+        pins: Vec<String>,
+
+        /// Print the sync report as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Search for skills
     Search {
@@ -66,26 +144,85 @@ enum Commands {
         #[arg(long, default_value = "80")]
         min_score: i64,
 
+        /// Rank by meaning using the local semantic model instead of keywords
+        #[arg(long, conflicts_with = "hybrid")]
+        semantic: bool,
+
+        /// Fuse keyword (BM25) and semantic rankings with reciprocal rank fusion
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Show every copy of a skill instead of collapsing duplicates across registries
+        #[arg(long)]
+        all_copies: bool,
+
+        /// Only show skills changed upstream within this window (e.g. 180d, 1y, 2025-06-01)
+        #[arg(long)]
+        updated_since: Option<String>,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
     },
     /// Show skill details
     Show {
-        /// Skill slug
+        /// Skill slug, or registry:slug to pick a specific registry
         slug: String,
+        /// Read SKILL.md from the registry's checkout or directory as it is now, not as last synced
+        #[arg(long)]
+        fresh: bool,
     },
     /// Get install URL for a skill
     Url {
-        /// Skill slug
+        /// Skill slug, or registry:slug to pick a specific registry
         slug: String,
+
+        /// Print a URL pinned to the upstream commit that was indexed
+        #[arg(long)]
+        permalink: bool,
     },
-    /// List top skills by stars
+    /// List top skills by stars, installs or popularity
     Top {
         /// Number of results (default: 20)
         #[arg(short, long, default_value = "20")]
         limit: usize,
 
+        /// Ranking signal; popularity normalizes each metric within its registry,
+        /// updated sorts by the last upstream commit
+        #[arg(long, value_enum, default_value = "stars")]
+        by: TopOrder,
+
+        /// Only show skills changed upstream within this window (e.g. 180d, 1y, 2025-06-01)
+        #[arg(long)]
+        updated_since: Option<String>,
+
+        /// Only show trusted skills
+        #[arg(long)]
+        trusted: bool,
+
+        /// Minimum quality score (default: 80, set to 0 to show all)
+        #[arg(long, default_value = "80")]
+        min_score: i64,
+    },
+    /// Export or import a catalog snapshot, e.g. for machines without network access
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// List skills first seen recently (a what's-new feed)
+    New {
+        /// Window: 7d, 12h, 2w, a date (2026-01-31) or last-sync
+        #[arg(long, default_value = "7d")]
+        since: String,
+
+        /// Number of results (default: 50)
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+
+        /// Filter by registry
+        #[arg(short, long)]
+        registry: Option<String>,
+
         /// Only show trusted skills
         #[arg(long)]
         trusted: bool,
@@ -93,9 +230,83 @@ enum Commands {
         /// Minimum quality score (default: 80, set to 0 to show all)
         #[arg(long, default_value = "80")]
         min_score: i64,
+
+        /// Output as an Atom or RSS feed
+        #[arg(long, value_enum, conflicts_with = "json")]
+        feed: Option<FeedFormat>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum SnapshotCommands {
+    /// Write the catalog to a versioned .tar.gz with a manifest and checksums
+    Export {
+        /// Snapshot file to write
+        file: PathBuf,
+
+        /// Also include the synced skill directories (repos/, without git history)
+        #[arg(long)]
+        with_skills: bool,
+    },
+    /// Replace the catalog with a snapshot's and rebuild the search index
+    Import {
+        /// Snapshot archive, or a copy of another machine's skills.db
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Show reports of past syncs, newest first
+    Log {
+        /// Number of runs (default: 10)
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show each registry's last sync and attempt, commit, skill count and staleness
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TopOrder {
+    Stars,
+    Installs,
+    Downloads,
+    Forks,
+    Popularity,
+    Updated,
+}
+
+impl TopOrder {
+    fn metric(self) -> Option<MetricKind> {
+        match self {
+            Self::Stars => Some(MetricKind::Stars),
+            Self::Installs => Some(MetricKind::Installs),
+            Self::Downloads => Some(MetricKind::Downloads),
+            Self::Forks => Some(MetricKind::Forks),
+            Self::Popularity | Self::Updated => None,
+        }
+    }
+}
+
 fn get_data_dir(cli_path: Option<PathBuf>) -> Result<PathBuf> {
     if let Some(p) = cli_path {
         return Ok(p);
@@ -109,6 +320,199 @@ fn get_data_dir(cli_path: Option<PathBuf>) -> Result<PathBuf> {
     Ok(data_dir)
 }
 
+/// Resolves a `slug` or `registry:slug` argument to a single skill.
+///
+/// A bare slug that exists in several registries resolves to the trusted copy, then
+/// the highest quality one. If that still leaves a tie, the candidates are listed and
+/// the process exits with status 2 rather than guessing.
+fn resolve_skill(db: &db::Database, quality_scores: &QualityScores, spec: &str) -> Result<Option<db::Skill>> {
+    if let Some((registry, slug)) = spec.split_once(':') {
+        return db.get_skill(registry, slug);
+    }
+
+    let mut candidates: Vec<_> = db
+        .get_skills_by_slug(spec)?
+        .into_iter()
+        .map(|s| {
+            let quality_score = quality_scores
+                .get_score(&s.registry, &s.slug)
+                .or_else(|| quality_scores.get_score(&s.registry, &s.name))
+                .unwrap_or(0);
+            (s, quality_score)
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        (b.0.trusted, b.1)
+            .cmp(&(a.0.trusted, a.1))
+            .then_with(|| a.0.registry.cmp(&b.0.registry))
+    });
+
+    if candidates.len() > 1 {
+        let (first, second) = (&candidates[0], &candidates[1]);
+        if (first.0.trusted, first.1) == (second.0.trusted, second.1) {
+            eprintln!("Ambiguous skill '{}' exists in several registries:", spec);
+            for (s, quality_score) in &candidates {
+                let trust_icon = if s.trusted { "✓" } else { "⚠" };
+                eprintln!("  [{}] {}:{} [Q:{}]", trust_icon, s.registry, s.slug, quality_score);
+            }
+            eprintln!("Use registry:slug to choose one.");
+            std::process::exit(2);
+        }
+        let others: Vec<&str> = candidates[1..].iter().map(|(s, _)| s.registry.as_str()).collect();
+        eprintln!(
+            "Note: '{}' also exists in {}; showing {}:{} (use registry:slug to choose)",
+            spec,
+            others.join(", "),
+            first.0.registry,
+            first.0.slug
+        );
+    }
+
+    Ok(candidates.into_iter().next().map(|(s, _)| s))
+}
+
+/// Resolves an `--updated-since` window to a timestamp (0 when not given).
+fn parse_updated_since(db: &db::Database, spec: Option<&str>) -> Result<i64> {
+    let Some(spec) = spec else { return Ok(0) };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    let last_sync = db.get_sync_runs(1)?.first().map(|r| r.started_at);
+    feed::parse_since(spec, now, last_sync)
+}
+
+fn stale_registries(
+    db: &db::Database,
+    registries: &[github::Registry],
+    max_age: i64,
+) -> Result<Vec<sync::StaleRegistry>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    sync::stale_registries(db, registries, max_age, now)
+}
+
+/// Reads a skill's SKILL.md from its registry's source as it is now, without a sync.
+async fn fetch_skill_md(
+    db: &db::Database,
+    repos_dir: &std::path::Path,
+    registries: &[github::Registry],
+    skill: &db::Skill,
+) -> Result<String> {
+    let source = source::sources(registries)
+        .into_iter()
+        .find(|s| s.name() == skill.registry)
+        .ok_or_else(|| anyhow::anyhow!("registry '{}' is not configured", skill.registry))?;
+    let ctx = source::SourceContext {
+        repos_dir: repos_dir.to_path_buf(),
+        http: http::HttpClient::new(db.get_http_cache()?, None)?,
+        limiter: std::sync::Arc::new(tokio::sync::Semaphore::new(1)),
+        cursor: None,
+    };
+    source.fetch_skill(&ctx, &skill.slug).await
+}
+
+/// Names of the registries that sync without the network.
+fn local_registries(registries: &[github::Registry]) -> Vec<String> {
+    registries.iter().filter(|r| r.is_local()).map(|r| r.name.clone()).collect()
+}
+
+/// Fills an empty database on first launch: from a catalog snapshot if one is bundled
+/// or named by `SKILL_SEARCH_SNAPSHOT`, otherwise by syncing (only local registries
+/// when offline). Fails with directions when that still leaves no skills.
+async fn bootstrap(
+    db: &mut db::Database,
+    data_dir: &std::path::Path,
+    repos_dir: &std::path::Path,
+    search_index: &index::SearchIndex,
+    quality_scores: &QualityScores,
+    registries: &[github::Registry],
+    offline: bool,
+) -> Result<()> {
+    if let Some(path) = snapshot::find_bootstrap(data_dir) {
+        tracing::info!("First launch detected, loading snapshot {}", path.display());
+        let imported = snapshot::import(db, data_dir, repos_dir, &path)?;
+        search_index.rebuild(db)?;
+        tracing::info!("Loaded {} skills from {}", imported.skills, path.display());
+        return Ok(());
+    }
+
+    let only = if offline { Some(local_registries(registries)) } else { None };
+    if only.as_ref().is_none_or(|local| !local.is_empty()) {
+        tracing::info!("First launch detected, syncing skills...");
+        let options = sync::SyncOptions {
+            registries: registries.to_vec(),
+            only,
+            ..Default::default()
+        };
+        run_sync(db, repos_dir, search_index, quality_scores, &options).await?;
+    }
+    if db.needs_initial_sync()? {
+        let cause = if offline {
+            "--offline was given"
+        } else {
+            "no registry could be synced; check your network connection"
+        };
+        anyhow::bail!(
+            "no local skill data in {} ({}). Sync on a connected machine first, or bootstrap from a \
+             catalog snapshot: run `snapshot import <file>`, copy it to {}, or point {} at it",
+            data_dir.display(),
+            cause,
+            data_dir.join(snapshot::SNAPSHOT_FILES[0]).display(),
+            snapshot::SNAPSHOT_ENV
+        );
+    }
+    Ok(())
+}
+
+/// Whether a sync is in its fetch phase, where Ctrl-C cancels fetches instead of quitting
+static FETCHING: AtomicBool = AtomicBool::new(false);
+
+/// Installs the Ctrl-C listener, once for the rest of the process: the first Ctrl-C during
+/// the fetch phase abandons pending clones and fetches and keeps what was synced; a second
+/// one, or any outside the fetch phase, quits.
+fn listen_for_interrupts() {
+    static LISTENER: Once = Once::new();
+    LISTENER.call_once(|| {
+        tokio::spawn(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if !FETCHING.swap(false, Ordering::SeqCst) {
+                    std::process::exit(130);
+                }
+                tracing::warn!("Interrupted: cancelling git fetches, press Ctrl-C again to quit");
+                git::cancel();
+            }
+        });
+    });
+}
+
+async fn run_sync(
+    db: &mut db::Database,
+    repos_dir: &std::path::Path,
+    search_index: &index::SearchIndex,
+    quality_scores: &QualityScores,
+    options: &sync::SyncOptions,
+) -> Result<report::SyncReport> {
+    let started = std::time::Instant::now();
+    let before = report::Snapshot::capture(db, quality_scores)?;
+
+    listen_for_interrupts();
+    FETCHING.store(true, Ordering::SeqCst);
+    let result = sync::sync_all(db, repos_dir, options).await;
+    FETCHING.store(false, Ordering::SeqCst);
+    let mut report = result?;
+    dedup::cluster_duplicates(db, quality_scores)?;
+    typosquat::detect_impersonators(db)?;
+    search_index.rebuild(db)?;
+
+    let after = report::Snapshot::capture(db, quality_scores)?;
+    report.record_changes(&before, &after);
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report.id = db.save_sync_run(&report)?;
+    tracing::info!("Sync finished: {}", report.summary());
+    Ok(report)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -128,24 +532,217 @@ async fn main() -> Result<()> {
     let mut db = db::Database::open(&db_path)?;
     let search_index = index::SearchIndex::open_or_create(&index_path)?;
     let quality_scores = QualityScores::load();
+    let config = config::Config::load(&data_dir.join(config::CONFIG_FILE))?;
+    let registries = config.registries()?;
+    let max_age = config.max_age()?;
 
-    // Auto-sync on first launch
-    if db.needs_initial_sync()? {
-        tracing::info!("First launch detected, syncing skills...");
-        github::sync_all_registries(&mut db, &repos_dir).await?;
-        skillssh::sync_skillssh(&mut db).await?;
-        search_index.rebuild(&db)?;
+    // Fill an empty database on first launch, and check freshness (sync and snapshot do their own)
+    let manages_data = matches!(cli.command, Commands::Sync { .. } | Commands::Snapshot { .. });
+    if !manages_data && db.needs_initial_sync()? {
+        // Another process may already be doing the first sync; wait for it rather than repeat it
+        let _lock = lock::SyncLock::wait(&data_dir)?;
+        if db.needs_initial_sync()? {
+            bootstrap(&mut db, &data_dir, &repos_dir, &search_index, &quality_scores, &registries, cli.offline)
+                .await?;
+        }
+    } else if !manages_data {
+        let stale = stale_registries(&db, &registries, max_age)?;
+        if cli.auto_sync && !stale.is_empty() {
+            let _lock = lock::SyncLock::wait(&data_dir)?;
+            // A sync we waited for may have refreshed them already
+            let stale = stale_registries(&db, &registries, max_age)?;
+            if !stale.is_empty() {
+                let names: Vec<String> = stale.into_iter().map(|s| s.name).collect();
+                tracing::info!("Refreshing stale registries: {}", names.join(", "));
+                let options = sync::SyncOptions {
+                    registries: registries.clone(),
+                    only: Some(names),
+                    ..Default::default()
+                };
+                // Stale results beat none: a failed refresh still answers from what we have
+                if let Err(e) = run_sync(&mut db, &repos_dir, &search_index, &quality_scores, &options).await {
+                    tracing::warn!("Auto-sync failed: {:#}", e);
+                }
+            }
+        } else if !stale.is_empty() {
+            let stale: Vec<String> = stale.iter().map(|s| s.to_string()).collect();
+            let hint = if cli.offline {
+                ""
+            } else {
+                "; run `safe-skill-search sync` or pass --auto-sync"
+            };
+            eprintln!("Warning: stale data for {}{}", stale.join(", "), hint);
+        }
     }
 
     match cli.command {
-        Commands::Sync { force } => {
+        Commands::Sync {
+            command: Some(SyncCommands::Log { limit, json }),
+            ..
+        } => {
+            let runs = db.get_sync_runs(limit)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&runs)?);
+            } else if runs.is_empty() {
+                println!("No syncs recorded yet.");
+            } else {
+                for run in &runs {
+                    println!("#{} {}: {}", run.id, report::format_timestamp(run.started_at), run.summary());
+                }
+            }
+        }
+        Commands::Sync {
+            command: Some(SyncCommands::Status { json }),
+            ..
+        } => {
+            let states = db.get_sync_states()?;
+            let counts = db.count_skills_by_registry()?;
+            let stale = stale_registries(&db, &registries, max_age)?;
+            let mut sources: Vec<(&str, &str, Option<&str>)> = registries
+                .iter()
+                .map(|r| (r.name.as_str(), r.kind.as_str(), r.git_ref.as_deref()))
+                .collect();
+            // Registries without a git repo (skills.sh), listed even before their first sync
+            for name in std::iter::once("skillssh").chain(states.iter().map(|s| s.registry.as_str())) {
+                if !sources.iter().any(|(n, ..)| *n == name) {
+                    sources.push((name, "api", None));
+                }
+            }
+            let syncers = source::sources(&registries);
+            let rows: Vec<serde_json::Value> = sources
+                .iter()
+                .map(|&(name, kind, git_ref)| {
+                    let state = states.iter().find(|s| s.registry == name);
+                    // How far an interrupted sync got, in the source's own terms
+                    let resume = match syncers.iter().find(|s| s.name() == name) {
+                        Some(syncer) => db.get_sync_cursor(name)?.and_then(|c| syncer.describe_cursor(&c)),
+                        None => None,
+                    };
+                    let max_age = registries
+                        .iter()
+                        .find(|r| r.name == name)
+                        .and_then(|r| r.max_age)
+                        .unwrap_or(max_age);
+                    Ok(serde_json::json!({
+                        "registry": name,
+                        "type": kind,
+                        "ref": git_ref,
+                        "commit": state.and_then(|s| s.commit.clone()),
+                        "last_sync": state.and_then(|s| s.last_sync),
+                        "last_attempt": state.and_then(|s| s.last_attempt),
+                        "error": state.and_then(|s| s.last_error.clone()),
+                        "skills": counts.get(name).copied().unwrap_or(0),
+                        "stale": stale.iter().any(|s| s.name == name),
+                        "max_age_secs": max_age,
+                        "resume": resume,
+                    }))
+                })
+                .collect::<Result<_>>()?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+            } else {
+                for row in &rows {
+                    let commit = row["commit"].as_str().map(report::short_sha).unwrap_or("-");
+                    let last_sync = row["last_sync"]
+                        .as_i64()
+                        .map(report::format_timestamp)
+                        .unwrap_or_else(|| "never".to_string());
+                    let pinned = match (row["type"].as_str(), row["ref"].as_str()) {
+                        (_, Some(r)) => format!(" (pinned to {})", r),
+                        (Some(kind @ ("path" | "archive" | "plugin")), None) => format!(" ({})", kind),
+                        _ => String::new(),
+                    };
+                    let stale = if row["stale"].as_bool() == Some(true) {
+                        format!(", stale (max age {})", sync::format_age(row["max_age_secs"].as_i64().unwrap_or(0)))
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "{:<20} {:<12} {:>6} skills  synced {}{}{}",
+                        row["registry"].as_str().unwrap_or(""),
+                        commit,
+                        row["skills"].as_u64().unwrap_or(0),
+                        last_sync,
+                        stale,
+                        pinned
+                    );
+                    if let (Some(attempt), Some(error)) = (row["last_attempt"].as_i64(), row["error"].as_str()) {
+                        println!("    last attempt {} failed: {}", report::format_timestamp(attempt), error);
+                    }
+                    if let Some(resume) = row["resume"].as_str() {
+                        println!("    interrupted: {}", resume);
+                    }
+                }
+            }
+        }
+        Commands::Sync {
+            command: None,
+            force,
+            jobs,
+            pins,
+            json,
+        } => {
+            let _lock = lock::SyncLock::acquire(&data_dir)?;
             if force {
                 db.clear_sync_state()?;
             }
-            github::sync_all_registries(&mut db, &repos_dir).await?;
-            skillssh::sync_skillssh(&mut db).await?;
+            let mut registries = registries;
+            config::pin_refs(&mut registries, &pins)?;
+            let only = if cli.offline {
+                let local = local_registries(&registries);
+                if local.is_empty() {
+                    anyhow::bail!(
+                        "nothing to sync offline: only path and archive registries, and git registries \
+                         with a file:// repo_url, sync without the network"
+                    );
+                }
+                Some(local)
+            } else {
+                None
+            };
+            let options = sync::SyncOptions {
+                concurrency: jobs,
+                registries,
+                only,
+            };
+            let report = run_sync(&mut db, &repos_dir, &search_index, &quality_scores, &options).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+        }
+        Commands::Snapshot {
+            command: SnapshotCommands::Export { file, with_skills },
+        } => {
+            // Skill directories are copied file by file, so keep a sync from swapping them meanwhile
+            let _lock = if with_skills { Some(lock::SyncLock::wait(&data_dir)?) } else { None };
+            let manifest = snapshot::export(&db, &data_dir, &repos_dir, &file, with_skills)?;
+            println!(
+                "Exported {} skills and {} files to {}",
+                manifest.skills,
+                manifest.files.len(),
+                file.display()
+            );
+        }
+        Commands::Snapshot {
+            command: SnapshotCommands::Import { file },
+        } => {
+            let _lock = lock::SyncLock::acquire(&data_dir)?;
+            let imported = snapshot::import(&mut db, &data_dir, &repos_dir, &file)?;
             search_index.rebuild(&db)?;
-            tracing::info!("Sync complete");
+            let created = imported
+                .created_at
+                .map(|t| format!(" exported {}", report::format_timestamp(t)))
+                .unwrap_or_default();
+            println!(
+                "Imported {} skills and {} skill files from {}{}",
+                imported.skills,
+                imported.skill_files,
+                file.display(),
+                created
+            );
         }
         Commands::Search {
             query,
@@ -153,31 +750,71 @@ async fn main() -> Result<()> {
             registry,
             trusted,
             min_score,
+            semantic,
+            hybrid,
+            all_copies,
+            updated_since,
             json,
         } => {
-            let results = search_index.search(&query, limit * 4, registry.as_deref())?;
+            let updated_since = parse_updated_since(&db, updated_since.as_deref())?;
+            let candidates = limit * 4;
+            let results = if semantic || hybrid {
+                let model = semantic::SemanticModel::load_for_query(&db, &query)?;
+                let semantic_results = model.search(&query, candidates, registry.as_deref());
+                if hybrid {
+                    let keyword_results = search_index.search(&query, candidates, registry.as_deref())?;
+                    semantic::reciprocal_rank_fusion(&[keyword_results, semantic_results], candidates)
+                } else {
+                    semantic_results
+                }
+            } else {
+                search_index.search(&query, candidates, registry.as_deref())?
+            };
 
+            let mut seen = HashSet::new();
             let enriched: Vec<_> = results
                 .into_iter()
                 .filter_map(|r| {
-                    db.get_skill(&r.registry, &r.slug).ok().flatten().map(|s| {
-                        let quality_score = quality_scores
-                            .get_score(&s.registry, &s.slug)
-                            .or_else(|| quality_scores.get_score(&s.registry, &s.name))
-                            .unwrap_or(0);
-                        
-                        serde_json::json!({
-                            "slug": s.slug,
-                            "name": s.name,
-                            "registry": s.registry,
-                            "description": s.description,
-                            "github_url": s.github_url,
-                            "stars": s.stars,
-                            "trusted": s.trusted,
-                            "search_score": r.score,
-                            "quality_score": quality_score,
-                        })
-                    })
+                    let mut skill = db.get_skill(&r.registry, &r.slug).ok().flatten()?;
+                    if !all_copies {
+                        // Show the canonical copy in place of a duplicate, unless it's
+                        // outside the requested registry
+                        if let Some(canonical) = db.get_canonical_skill(skill.id).ok().flatten() {
+                            if registry.as_deref().is_none_or(|reg| reg == canonical.registry) {
+                                skill = canonical;
+                            }
+                        }
+                        if !seen.insert(skill.id) {
+                            return None;
+                        }
+                    }
+                    if skill.updated_at < updated_since {
+                        return None;
+                    }
+                    let quality_score = quality_scores
+                        .get_score(&skill.registry, &skill.slug)
+                        .or_else(|| quality_scores.get_score(&skill.registry, &skill.name))
+                        .unwrap_or(0);
+                    let warnings: Vec<String> = db
+                        .get_findings(skill.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|f| f.detail)
+                        .collect();
+
+                    Some(serde_json::json!({
+                        "slug": skill.slug,
+                        "name": skill.name,
+                        "registry": skill.registry,
+                        "description": skill.description,
+                        "github_url": skill.github_url,
+                        "permalink": skill.permalink,
+                        "stars": skill.stars,
+                        "trusted": skill.trusted,
+                        "search_score": r.score,
+                        "quality_score": quality_score,
+                        "warnings": warnings,
+                    }))
                 })
                 .filter(|r| !trusted || r["trusted"].as_bool().unwrap_or(false))
                 .filter(|r| r["quality_score"].as_i64().unwrap_or(0) >= min_score)
@@ -208,13 +845,16 @@ async fn main() -> Result<()> {
                             r["description"].as_str().unwrap_or("")
                         );
                         println!("   {}", r["github_url"].as_str().unwrap_or(""));
+                        for warning in r["warnings"].as_array().into_iter().flatten() {
+                            println!("   ⚠ WARNING: {}", warning.as_str().unwrap_or(""));
+                        }
                         println!();
                     }
                 }
             }
         }
-        Commands::Show { slug } => {
-            let skill = db.get_skill_by_slug(&slug)?;
+        Commands::Show { slug, fresh } => {
+            let skill = resolve_skill(&db, &quality_scores, &slug)?;
             match skill {
                 Some(s) => {
                     let quality_score = quality_scores
@@ -225,12 +865,54 @@ async fn main() -> Result<()> {
                     println!("Name: {}", s.name);
                     println!("Registry: {}", s.registry);
                     println!("Trusted: {}", if s.trusted { "yes" } else { "no" });
-                    println!("Stars: {}", s.stars);
+                    let metrics = db.get_metrics(s.id)?;
+                    if !metrics.iter().any(|m| m.kind == MetricKind::Stars) {
+                        println!("Stars: {}", s.stars);
+                    }
+                    for m in &metrics {
+                        println!("{}: {} (from {})", m.kind.label(), m.value, m.source);
+                    }
                     println!("Quality Score: {}", quality_score);
                     println!("Description: {}", s.description);
                     println!("URL: {}", s.github_url);
-                    if !s.skill_md.is_empty() {
-                        println!("\n--- SKILL.md ---\n{}", s.skill_md);
+                    if let Some(permalink) = &s.permalink {
+                        println!("Permalink: {}", permalink);
+                    }
+                    if let Some(commit) = &s.commit {
+                        println!(
+                            "Last commit: {} ({}) by {}",
+                            report::short_sha(&commit.sha),
+                            report::format_timestamp(commit.time),
+                            commit.author
+                        );
+                    }
+                    let copies = db.get_duplicate_skills(s.id)?;
+                    if !copies.is_empty() {
+                        let also: Vec<String> = copies
+                            .iter()
+                            .map(|c| format!("{}:{}", c.registry, c.slug))
+                            .collect();
+                        println!("Also available in: {}", also.join(", "));
+                    }
+                    for finding in db.get_findings(s.id)? {
+                        println!("WARNING [{}]: {}", finding.severity, finding.detail);
+                    }
+                    let skill_md = if fresh {
+                        match fetch_skill_md(&db, &repos_dir, &registries, &s).await {
+                            Ok(skill_md) => skill_md,
+                            Err(e) => {
+                                eprintln!(
+                                    "Note: couldn't read {}:{} from its source ({:#}); showing the synced copy",
+                                    s.registry, s.slug, e
+                                );
+                                s.skill_md.clone()
+                            }
+                        }
+                    } else {
+                        s.skill_md.clone()
+                    };
+                    if !skill_md.is_empty() {
+                        println!("\n--- SKILL.md ---\n{}", skill_md);
                     }
                 }
                 None => {
@@ -239,9 +921,16 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Url { slug } => {
-            let skill = db.get_skill_by_slug(&slug)?;
+        Commands::Url { slug, permalink } => {
+            let skill = resolve_skill(&db, &quality_scores, &slug)?;
             match skill {
+                Some(s) if permalink => match s.permalink {
+                    Some(url) => println!("{}", url),
+                    None => {
+                        eprintln!("No permalink recorded for {}:{}; run sync to record one", s.registry, s.slug);
+                        std::process::exit(1);
+                    }
+                },
                 Some(s) => println!("{}", s.github_url),
                 None => {
                     eprintln!("Skill not found: {}", slug);
@@ -249,11 +938,121 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Top { limit, trusted, min_score } => {
+        Commands::New {
+            since,
+            limit,
+            registry,
+            trusted,
+            min_score,
+            feed,
+            json,
+        } => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            let last_sync = db.get_sync_runs(1)?.first().map(|r| r.started_at);
+            let since = feed::parse_since(&since, now, last_sync)?;
+
+            let skills: Vec<_> = db
+                .get_skills_first_seen_since(since, registry.as_deref())?
+                .into_iter()
+                .filter(|(s, _)| !trusted || s.trusted)
+                .filter_map(|(s, first_seen)| {
+                    let quality_score = quality_scores
+                        .get_score(&s.registry, &s.slug)
+                        .or_else(|| quality_scores.get_score(&s.registry, &s.name))
+                        .unwrap_or(0);
+                    (quality_score >= min_score).then_some((s, first_seen, quality_score))
+                })
+                .take(limit)
+                .collect();
+
+            if let Some(format) = feed {
+                let entries: Vec<feed::FeedEntry> = skills
+                    .iter()
+                    .map(|(s, first_seen, quality_score)| feed::FeedEntry {
+                        title: format!("{} ({})", s.name, s.registry),
+                        link: s.github_url.clone(),
+                        summary: format!(
+                            "{} [Q:{}, {}]",
+                            s.description,
+                            quality_score,
+                            if s.trusted { "trusted" } else { "untrusted" }
+                        ),
+                        registry: s.registry.clone(),
+                        slug: s.slug.clone(),
+                        first_seen: *first_seen,
+                    })
+                    .collect();
+                match format {
+                    FeedFormat::Atom => print!("{}", feed::render_atom(&entries, now)),
+                    FeedFormat::Rss => print!("{}", feed::render_rss(&entries, now)),
+                }
+            } else if json {
+                let results: Vec<_> = skills
+                    .iter()
+                    .map(|(s, first_seen, quality_score)| {
+                        serde_json::json!({
+                            "slug": s.slug,
+                            "name": s.name,
+                            "registry": s.registry,
+                            "description": s.description,
+                            "github_url": s.github_url,
+                            "trusted": s.trusted,
+                            "quality_score": quality_score,
+                            "first_seen": first_seen,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else if skills.is_empty() {
+                println!(
+                    "No new skills since {} with score >= {}. Try --min-score 0 to see all.",
+                    report::format_timestamp(since),
+                    min_score
+                );
+            } else {
+                for (i, (s, first_seen, quality_score)) in skills.iter().enumerate() {
+                    let trust_icon = if s.trusted { "✓" } else { "⚠" };
+                    println!(
+                        "{}. [{}] {} ({}) [Q:{}] first seen {} - {}",
+                        i + 1,
+                        trust_icon,
+                        s.name,
+                        s.registry,
+                        quality_score,
+                        report::format_timestamp(*first_seen),
+                        s.description
+                    );
+                    println!("   {}", s.github_url);
+                }
+            }
+        }
+        Commands::Top {
+            limit,
+            by,
+            updated_since,
+            trusted,
+            min_score,
+        } => {
+            let updated_since = parse_updated_since(&db, updated_since.as_deref())?;
+            let all_metrics = db.get_all_metrics()?;
+            let popularity = metrics::popularity_scores(&all_metrics);
+            let mut values: HashMap<(i64, MetricKind), i64> = HashMap::new();
+            for m in &all_metrics {
+                values.insert((m.skill_id, m.kind), m.value);
+            }
+            let metric_value = |s: &db::Skill, kind: MetricKind| match values.get(&(s.id, kind)) {
+                Some(v) => *v,
+                None if kind == MetricKind::Stars => s.stars,
+                None => 0,
+            };
+
             let all_skills = db.get_all_skills()?;
             let mut skills: Vec<_> = all_skills
                 .into_iter()
                 .filter(|s| !trusted || s.trusted)
+                .filter(|s| s.updated_at >= updated_since)
                 .filter_map(|s| {
                     let quality_score = quality_scores
                         .get_score(&s.registry, &s.slug)
@@ -268,19 +1067,33 @@ async fn main() -> Result<()> {
                 })
                 .collect();
             
-            skills.sort_by(|a, b| b.0.stars.cmp(&a.0.stars));
+            let popularity_of = |s: &db::Skill| popularity.get(&s.id).copied().unwrap_or(0.0);
+            match by.metric() {
+                Some(kind) => skills.sort_by_key(|s| std::cmp::Reverse(metric_value(&s.0, kind))),
+                None if by == TopOrder::Updated => skills.sort_by_key(|s| std::cmp::Reverse(s.0.updated_at)),
+                None => skills.sort_by(|a, b| popularity_of(&b.0).total_cmp(&popularity_of(&a.0))),
+            }
 
             if skills.is_empty() {
                 println!("No skills found with score >= {}. Try --min-score 0 to see all.", min_score);
             } else {
                 for (i, (s, quality_score)) in skills.iter().take(limit).enumerate() {
                     let trust_icon = if s.trusted { "✓" } else { "⚠" };
+                    let rank = match by {
+                        TopOrder::Stars => format!("★{}", metric_value(s, MetricKind::Stars)),
+                        TopOrder::Popularity => format!("[P:{:.0}]", popularity_of(s) * 100.0),
+                        TopOrder::Updated => format!("updated {}", report::format_timestamp(s.updated_at)),
+                        _ => {
+                            let kind = by.metric().unwrap_or(MetricKind::Stars);
+                            format!("{} {}", metric_value(s, kind), kind.as_str())
+                        }
+                    };
                     println!(
-                        "{}. [{}] {} ★{} ({}) [Q:{}] - {}",
+                        "{}. [{}] {} {} ({}) [Q:{}] - {}",
                         i + 1,
                         trust_icon,
                         s.name,
-                        s.stars,
+                        rank,
                         s.registry,
                         quality_score,
                         s.description
@@ -0,0 +1,287 @@
+use crate::feed;
+use crate::forge::Forge;
+use crate::github::{self, MetricsApi, Registry, RegistryKind};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Registry configuration file, read from the data directory.
+pub const CONFIG_FILE: &str = "registries.yaml";
+
+/// How long synced data stays fresh when the config file doesn't set `max_age`.
+pub const DEFAULT_MAX_AGE: i64 = 7 * 86400;
+
+/// Contents of `registries.yaml`.
+///
+/// ```yaml
+/// max_age: 3d                # warn when a registry's last sync is older; default 7d
+/// registries:
+///   - name: anthropic        # a built-in registry: only the given fields change
+///     ref: v1.2.0            # branch, tag or commit to check out
+///     max_age: 12h           # overrides the default above
+///   - name: internal         # a new registry
+///     repo_url: https://gitlab.example.com/team/skills.git
+///     skills_path: skills
+///     trusted: true
+///     forge: gitlab
+///   - name: clawdhub-mirror  # a git mirror, with metrics from the registry's API
+///     repo_url: https://git.example.com/mirrors/openclaw-skills.git
+///     metrics: clawdhub
+///   - name: shared           # a local directory, scanned in place
+///     type: path
+///     path: /mnt/shared/skills
+///   - name: release          # a .tar.gz or .zip, extracted on sync
+///     type: archive
+///     path: file:///srv/releases/skills-1.4.tar.gz
+///     skills_path: skills
+///   - name: intranet         # an external command printing skills as JSON Lines
+///     type: plugin
+///     command: /opt/skills/intranet-plugin
+///     args: [--team, platform]
+///     timeout_secs: 600
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Default staleness limit for every registry, including skills.sh (e.g. `7d`).
+    pub max_age: Option<String>,
+    #[serde(default)]
+    pub registries: Vec<RegistryEntry>,
+}
+
+/// One registry in the config file; unset fields keep the built-in value.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryEntry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: Option<RegistryKind>,
+    pub repo_url: Option<String>,
+    pub path: Option<String>,
+    pub skills_path: Option<String>,
+    pub trusted: Option<bool>,
+    pub forge: Option<Forge>,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub timeout_secs: Option<u64>,
+    pub max_age: Option<String>,
+    pub metrics: Option<MetricsApi>,
+}
+
+impl Config {
+    /// Reads the config file; a missing file means the built-in registries as they are.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("invalid config {:?}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(text)?)
+    }
+
+    /// How long a registry's data stays fresh when its entry doesn't set `max_age`.
+    pub fn max_age(&self) -> Result<i64> {
+        match &self.max_age {
+            Some(age) => feed::parse_age(age).context("invalid max_age"),
+            None => Ok(DEFAULT_MAX_AGE),
+        }
+    }
+
+    /// The built-in registries with the config file's overrides and additions applied.
+    pub fn registries(&self) -> Result<Vec<Registry>> {
+        let mut registries = github::builtin_registries();
+        for entry in &self.registries {
+            match registries.iter_mut().find(|r| r.name == entry.name) {
+                Some(registry) => entry.apply(registry)?,
+                None => {
+                    let kind = entry.kind.unwrap_or_default();
+                    match kind {
+                        RegistryKind::Git if entry.repo_url.is_none() => {
+                            anyhow::bail!("registry '{}' is not built in and needs a repo_url", entry.name)
+                        }
+                        RegistryKind::Path | RegistryKind::Archive if entry.path.is_none() => {
+                            anyhow::bail!("{} registry '{}' needs a path", kind.as_str(), entry.name)
+                        }
+                        RegistryKind::Plugin if entry.command.is_none() => {
+                            anyhow::bail!("plugin registry '{}' needs a command", entry.name)
+                        }
+                        _ => {}
+                    }
+                    let mut registry = Registry {
+                        name: entry.name.clone(),
+                        kind,
+                        repo_url: String::new(),
+                        path: None,
+                        // Local sources usually hold skills at their top level
+                        skills_path: if kind == RegistryKind::Git { "skills" } else { "" }.to_string(),
+                        trusted: false,
+                        forge: None,
+                        git_ref: None,
+                        command: None,
+                        args: Vec::new(),
+                        timeout_secs: None,
+                        max_age: None,
+                        metrics: None,
+                    };
+                    entry.apply(&mut registry)?;
+                    registries.push(registry);
+                }
+            }
+        }
+        Ok(registries)
+    }
+}
+
+impl RegistryEntry {
+    fn apply(&self, registry: &mut Registry) -> Result<()> {
+        if let Some(kind) = self.kind {
+            registry.kind = kind;
+        }
+        if let Some(path) = &self.path {
+            registry.path = Some(path.clone());
+        }
+        if let Some(repo_url) = &self.repo_url {
+            registry.repo_url = repo_url.clone();
+        }
+        if let Some(skills_path) = &self.skills_path {
+            registry.skills_path = skills_path.clone();
+        }
+        if let Some(trusted) = self.trusted {
+            registry.trusted = trusted;
+        }
+        if self.forge.is_some() {
+            registry.forge = self.forge;
+        }
+        if self.git_ref.is_some() {
+            registry.git_ref = self.git_ref.clone();
+        }
+        if self.command.is_some() {
+            registry.command = self.command.clone();
+        }
+        if let Some(args) = &self.args {
+            registry.args = args.clone();
+        }
+        if self.timeout_secs.is_some() {
+            registry.timeout_secs = self.timeout_secs;
+        }
+        if self.metrics.is_some() {
+            registry.metrics = self.metrics;
+        }
+        if let Some(age) = &self.max_age {
+            let age = feed::parse_age(age).with_context(|| format!("invalid max_age for registry '{}'", self.name))?;
+            registry.max_age = Some(age);
+        }
+        Ok(())
+    }
+}
+
+/// Applies `--ref registry=REF` pins from the command line.
+pub fn pin_refs(registries: &mut [Registry], pins: &[String]) -> Result<()> {
+    for pin in pins {
+        let Some((name, git_ref)) = pin.split_once('=') else {
+            anyhow::bail!("invalid --ref '{}': expected registry=REF", pin);
+        };
+        let Some(registry) = registries.iter_mut().find(|r| r.name == name) else {
+            anyhow::bail!("unknown registry '{}' in --ref", name);
+        };
+        if registry.kind != RegistryKind::Git {
+            anyhow::bail!("--ref only applies to git registries, '{}' is a {} registry", name, registry.kind.as_str());
+        }
+        registry.git_ref = Some(git_ref.to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_and_additions() {
+        let config = Config::parse(
+            r#"
+registries:
+  - name: anthropic
+    ref: v1.2.0
+    max_age: 12h
+  - name: internal
+    repo_url: https://gitlab.example.com/team/skills.git
+    trusted: true
+  - name: mirror
+    repo_url: https://git.example.com/mirrors/openclaw-skills.git
+    metrics: clawdhub
+  - name: release
+    type: archive
+    path: file:///srv/releases/skills.tar.gz
+  - name: intranet
+    type: plugin
+    command: /opt/skills/intranet-plugin
+    args: [--team, platform]
+"#,
+        )
+        .unwrap();
+        let registries = config.registries().unwrap();
+
+        let anthropic = registries.iter().find(|r| r.name == "anthropic").unwrap();
+        assert_eq!(anthropic.git_ref.as_deref(), Some("v1.2.0"));
+        assert_eq!(anthropic.repo_url, "https://github.com/anthropics/skills.git");
+        assert!(anthropic.trusted);
+        assert_eq!(anthropic.max_age, Some(12 * 3600));
+        assert_eq!(config.max_age().unwrap(), DEFAULT_MAX_AGE);
+
+        let internal = registries.iter().find(|r| r.name == "internal").unwrap();
+        assert_eq!(internal.kind, RegistryKind::Git);
+        assert_eq!(internal.skills_path, "skills");
+        assert!(internal.trusted);
+        assert_eq!(internal.metrics, None);
+
+        // Metrics follow the config, not the registry's name
+        let mirror = registries.iter().find(|r| r.name == "mirror").unwrap();
+        assert_eq!(mirror.metrics, Some(MetricsApi::Clawdhub));
+        let clawdhub = registries.iter().find(|r| r.name == "clawdhub").unwrap();
+        assert_eq!(clawdhub.metrics, Some(MetricsApi::Clawdhub));
+
+        let intranet = registries.last().unwrap();
+        assert_eq!(intranet.kind, RegistryKind::Plugin);
+        assert_eq!(intranet.args, ["--team", "platform"]);
+
+        let release = registries.iter().find(|r| r.name == "release").unwrap();
+        assert_eq!(release.kind, RegistryKind::Archive);
+        assert_eq!(release.path.as_deref(), Some("file:///srv/releases/skills.tar.gz"));
+        assert_eq!(release.skills_path, "");
+        assert!(!release.trusted);
+    }
+
+    #[test]
+    fn test_rejects_incomplete_registries() {
+        let config = Config::parse("registries:\n  - name: mystery\n").unwrap();
+        assert!(config.registries().is_err());
+        let config = Config::parse("registries:\n  - name: shared\n    type: path\n").unwrap();
+        assert!(config.registries().is_err());
+        let config = Config::parse("registries:\n  - name: intranet\n    type: plugin\n").unwrap();
+        assert!(config.registries().is_err());
+        assert!(Config::parse("registries:\n  - name: jo\n    branch: main\n").is_err());
+        assert!(Config::parse("").unwrap().registries.is_empty());
+        let config = Config::parse("registries:\n  - name: jo\n    max_age: soon\n").unwrap();
+        assert!(config.registries().is_err());
+        assert!(Config::parse("max_age: 7x\n").unwrap().max_age().is_err());
+        assert_eq!(Config::parse("max_age: 2d\n").unwrap().max_age().unwrap(), 2 * 86400);
+    }
+
+    #[test]
+    fn test_pin_refs() {
+        let mut registries = github::builtin_registries();
+        pin_refs(&mut registries, &["jo=0123abc".to_string()]).unwrap();
+        assert_eq!(registries.iter().find(|r| r.name == "jo").unwrap().git_ref.as_deref(), Some("0123abc"));
+        assert!(pin_refs(&mut registries, &["nope=main".to_string()]).is_err());
+        assert!(pin_refs(&mut registries, &["jo".to_string()]).is_err());
+    }
+}
@@ -1,7 +1,14 @@
-use anyhow::Result;
-use rusqlite::{params, Connection};
+use crate::dedup::{fingerprint, FingerprintRow};
+use crate::http::{CachedResponse, HttpCache};
+use crate::metrics::{Metric, MetricKind};
+use crate::report::SyncReport;
+use crate::semantic::{blob_to_vector, vector_to_blob, DocVector, TermVector};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, Row};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Skill {
@@ -15,9 +22,123 @@ pub struct Skill {
     pub version: Option<String>,
     pub stars: i64,
     pub trusted: bool,
+    /// Time of the last upstream commit to the skill, or of the sync that found it
+    /// when there is no git history.
     pub updated_at: i64,
+    #[serde(default)]
+    pub commit: Option<CommitInfo>,
+    /// URL pinned to the upstream commit the skill was read from.
+    #[serde(default)]
+    pub permalink: Option<String>,
 }
 
+/// The last upstream commit that touched a skill's directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub time: i64,
+    pub author: String,
+}
+
+const SKILL_COLUMNS: &str = "id, slug, name, registry, description, skill_md, github_url, version, stars, trusted, \
+     updated_at, commit_sha, commit_time, commit_author, permalink";
+
+fn skill_from_row(row: &Row) -> rusqlite::Result<Skill> {
+    Ok(Skill {
+        id: row.get(0)?,
+        slug: row.get(1)?,
+        name: row.get(2)?,
+        registry: row.get(3)?,
+        description: row.get(4)?,
+        skill_md: row.get(5)?,
+        github_url: row.get(6)?,
+        version: row.get(7)?,
+        stars: row.get(8)?,
+        trusted: row.get::<_, i64>(9)? != 0,
+        updated_at: row.get(10)?,
+        commit: match row.get::<_, Option<String>>(11)? {
+            Some(sha) => Some(CommitInfo {
+                sha,
+                time: row.get(12)?,
+                author: row.get(13)?,
+            }),
+            None => None,
+        },
+        permalink: row.get(14)?,
+    })
+}
+
+/// Column names of `table` in the `schema` database (`main` or an attached one).
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+/// Tables copied by [`Database::import_catalog`], parents first. The semantic model
+/// is rebuilt with the index, and sync history and HTTP caches stay per machine.
+const CATALOG_TABLES: &[&str] = &["skills", "findings", "skill_metrics", "sync_state"];
+
+/// Adds a column to an existing table if an older database was created without it.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = table_columns(conn, "main", table)?.iter().any(|c| c == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+/// Databases from before metrics were tracked separately hold skills.sh install counts
+/// in `skills.stars`, where they'd show as stars; moves them to the `installs` metric.
+fn move_skillssh_installs(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "INSERT OR IGNORE INTO skill_metrics (skill_id, metric, value, source, fetched_at)
+             SELECT id, 'installs', stars, 'https://skills.sh', updated_at
+             FROM skills WHERE registry = 'skillssh' AND stars > 0;
+         UPDATE skills SET stars = 0 WHERE registry = 'skillssh' AND stars > 0;",
+    )?;
+    Ok(())
+}
+
+/// A security-relevant observation about a skill, e.g. a suspected impersonator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub skill_id: i64,
+    pub kind: String,
+    pub severity: String,
+    pub detail: String,
+    pub created_at: i64,
+}
+
+/// Where a registry's last sync left it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncState {
+    pub registry: String,
+    /// When the registry last synced completely.
+    pub last_sync: Option<i64>,
+    /// The commit a git registry was synced at.
+    pub commit: Option<String>,
+    /// When a sync last tried the registry, whether or not it succeeded.
+    #[serde(default)]
+    pub last_attempt: Option<i64>,
+    /// What went wrong in that attempt, if anything.
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// What an upsert did to the stored row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// How long a statement waits on another process's lock before failing with "database is locked".
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct Database {
     conn: Connection,
 }
@@ -25,6 +146,11 @@ pub struct Database {
 impl Database {
     pub fn open(path: &Path) -> Result<Self> {
         let conn = Connection::open(path)?;
+        // A search started mid-sync waits for the writer's commit instead of failing
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Readers keep seeing the last committed state while a sync writes
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
 
         conn.execute_batch(
             r#"
@@ -53,12 +179,107 @@ impl Database {
                 last_sync INTEGER NOT NULL,
                 etag TEXT
             );
+
+            CREATE TABLE IF NOT EXISTS semantic_terms (
+                term TEXT PRIMARY KEY,
+                idf REAL NOT NULL,
+                vector BLOB NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS semantic_docs (
+                registry TEXT NOT NULL,
+                slug TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                vector BLOB NOT NULL,
+                PRIMARY KEY(registry, slug)
+            );
+
+            CREATE TABLE IF NOT EXISTS findings (
+                id INTEGER PRIMARY KEY,
+                skill_id INTEGER NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                severity TEXT NOT NULL,
+                detail TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_findings_skill ON findings(skill_id);
+
+            CREATE TABLE IF NOT EXISTS skill_metrics (
+                skill_id INTEGER NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+                metric TEXT NOT NULL,
+                value INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL,
+                PRIMARY KEY(skill_id, metric)
+            );
+
+            CREATE TABLE IF NOT EXISTS sync_runs (
+                id INTEGER PRIMARY KEY,
+                started_at INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                ok INTEGER NOT NULL,
+                report TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS http_cache (
+                url TEXT PRIMARY KEY,
+                etag TEXT NOT NULL,
+                body TEXT NOT NULL
+            );
             "#,
         )?;
 
+        add_column_if_missing(&conn, "skills", "content_hash", "TEXT")?;
+        add_column_if_missing(&conn, "skills", "simhash", "INTEGER")?;
+        add_column_if_missing(&conn, "skills", "canonical_id", "INTEGER")?;
+        add_column_if_missing(&conn, "sync_state", "cursor", "TEXT")?;
+        add_column_if_missing(&conn, "sync_state", "commit_sha", "TEXT")?;
+        add_column_if_missing(&conn, "skills", "first_seen", "INTEGER")?;
+        add_column_if_missing(&conn, "skills", "commit_sha", "TEXT")?;
+        add_column_if_missing(&conn, "skills", "commit_time", "INTEGER")?;
+        add_column_if_missing(&conn, "skills", "commit_author", "TEXT")?;
+        add_column_if_missing(&conn, "skills", "permalink", "TEXT")?;
+        add_column_if_missing(&conn, "sync_state", "last_attempt", "INTEGER")?;
+        add_column_if_missing(&conn, "sync_state", "last_error", "TEXT")?;
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_skills_content_hash ON skills(content_hash);
+             CREATE INDEX IF NOT EXISTS idx_skills_canonical ON skills(canonical_id);
+             CREATE INDEX IF NOT EXISTS idx_skills_first_seen ON skills(first_seen);
+             -- Skills synced before first_seen existed were first seen no later than their last sync
+             UPDATE skills SET first_seen = updated_at WHERE first_seen IS NULL;",
+        )?;
+        move_skillssh_installs(&conn)?;
+
         Ok(Self { conn })
     }
 
+    /// Runs `f` in a single transaction, committed only if it succeeds; a sync writes
+    /// each registry this way, so a crash never leaves one half-updated.
+    pub fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs `f` against a single read snapshot, so reads that span several queries
+    /// never mix the state before and after a concurrent sync's commit.
+    pub fn read_snapshot<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f(self)?;
+        tx.finish()?;
+        Ok(value)
+    }
+
     pub fn needs_initial_sync(&self) -> Result<bool> {
         let count: i64 = self
             .conn
@@ -67,7 +288,87 @@ impl Database {
     }
 
     pub fn clear_sync_state(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM sync_state", [])?;
+        self.conn.execute_batch("DELETE FROM sync_state; DELETE FROM http_cache;")?;
+        Ok(())
+    }
+
+    /// Writes a standalone copy of the catalog to `dest` for a snapshot: everything
+    /// [`Database::import_catalog`] reads, without this machine's sync history, HTTP
+    /// cache or semantic model. Returns the number of skills written.
+    pub fn export_catalog(&self, dest: &Path) -> Result<usize> {
+        // A consistent copy even while a sync is committing
+        self.conn
+            .execute("VACUUM INTO ?1", [dest.to_string_lossy()])
+            .with_context(|| format!("writing {}", dest.display()))?;
+        let copy = Connection::open(dest)?;
+        copy.pragma_update(None, "journal_mode", "DELETE")?;
+        copy.execute_batch(
+            "DELETE FROM sync_runs;
+             DELETE FROM http_cache;
+             DELETE FROM semantic_terms;
+             DELETE FROM semantic_docs;
+             UPDATE sync_state SET cursor = NULL;
+             VACUUM;",
+        )?;
+        let count: i64 = copy.query_row("SELECT COUNT(*) FROM skills", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Replaces the catalog (skills, findings, metrics and sync state) with the one in
+    /// another skills database, such as a copy of `skills.db` from a connected machine.
+    /// Columns only one side knows are left out, so older snapshots still load.
+    /// Returns the number of skills imported.
+    pub fn import_catalog(&mut self, path: &Path) -> Result<usize> {
+        self.conn
+            .execute("ATTACH DATABASE ?1 AS snapshot", [path.to_string_lossy()])
+            .with_context(|| format!("opening snapshot {}", path.display()))?;
+        let result = self.in_transaction(|db| {
+            if table_columns(&db.conn, "snapshot", "skills")?.is_empty() {
+                anyhow::bail!("{} is not a skill-search database", path.display());
+            }
+            for table in CATALOG_TABLES.iter().rev() {
+                db.conn.execute(&format!("DELETE FROM main.{}", table), [])?;
+            }
+            for table in CATALOG_TABLES {
+                let theirs = table_columns(&db.conn, "snapshot", table)?;
+                let columns: Vec<String> = table_columns(&db.conn, "main", table)?
+                    .into_iter()
+                    .filter(|c| theirs.contains(c))
+                    .collect();
+                if columns.is_empty() {
+                    continue;
+                }
+                let columns = columns.join(", ");
+                db.conn.execute(
+                    &format!("INSERT INTO main.{0} ({1}) SELECT {1} FROM snapshot.{0}", table, columns),
+                    [],
+                )?;
+            }
+            // Resume points belong to the exporting machine's HTTP cache
+            db.conn.execute("UPDATE sync_state SET cursor = NULL", [])?;
+            move_skillssh_installs(&db.conn)?;
+            let count: i64 = db.conn.query_row("SELECT COUNT(*) FROM skills", [], |row| row.get(0))?;
+            Ok(count as usize)
+        });
+        self.conn.execute("DETACH DATABASE snapshot", [])?;
+        result
+    }
+
+    /// Loads every cached API response, keyed by URL, for conditional requests.
+    pub fn get_http_cache(&self) -> Result<HttpCache> {
+        let mut stmt = self.conn.prepare("SELECT url, etag, body FROM http_cache")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, CachedResponse { etag: row.get(1)?, body: row.get(2)? }))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_http_cache(&self, url: &str, response: &CachedResponse) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO http_cache (url, etag, body) VALUES (?1, ?2, ?3)
+             ON CONFLICT(url) DO UPDATE SET etag = ?2, body = ?3",
+            params![url, response.etag, response.body],
+        )?;
         Ok(())
     }
 
@@ -75,7 +376,7 @@ impl Database {
     pub fn get_last_sync(&self, registry: &str) -> Result<Option<(i64, Option<String>)>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT last_sync, etag FROM sync_state WHERE registry = ?")?;
+            .prepare("SELECT last_sync, etag FROM sync_state WHERE registry = ? AND last_sync > 0")?;
         let result = stmt.query_row([registry], |row| Ok((row.get(0)?, row.get(1)?)));
         match result {
             Ok(r) => Ok(Some(r)),
@@ -86,17 +387,92 @@ impl Database {
 
     pub fn set_last_sync(&self, registry: &str, timestamp: i64, etag: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO sync_state (registry, last_sync, etag) VALUES (?, ?, ?)",
+            "INSERT INTO sync_state (registry, last_sync, etag) VALUES (?1, ?2, ?3)
+             ON CONFLICT(registry) DO UPDATE SET last_sync = ?2, etag = ?3",
             params![registry, timestamp, etag],
         )?;
         Ok(())
     }
 
-    pub fn upsert_skill(&self, skill: &Skill) -> Result<i64> {
+    /// Records the commit a git registry was last synced at.
+    pub fn set_sync_commit(&self, registry: &str, commit: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sync_state (registry, last_sync, commit_sha) VALUES (?1, 0, ?2)
+             ON CONFLICT(registry) DO UPDATE SET commit_sha = ?2",
+            params![registry, commit],
+        )?;
+        Ok(())
+    }
+
+    /// Records a sync's attempt at a registry and its error, replacing the last one.
+    pub fn set_sync_attempt(&self, registry: &str, timestamp: i64, error: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sync_state (registry, last_sync, last_attempt, last_error) VALUES (?1, 0, ?2, ?3)
+             ON CONFLICT(registry) DO UPDATE SET last_attempt = ?2, last_error = ?3",
+            params![registry, timestamp, error],
+        )?;
+        Ok(())
+    }
+
+    /// Number of skills stored for each registry.
+    pub fn count_skills_by_registry(&self) -> Result<HashMap<String, usize>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT registry, COUNT(*) FROM skills GROUP BY registry")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Returns the sync state of every registry that has one, by name.
+    pub fn get_sync_states(&self) -> Result<Vec<SyncState>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT registry, last_sync, commit_sha, last_attempt, last_error FROM sync_state ORDER BY registry",
+            )?;
+        let rows = stmt.query_map([], |row| {
+            let last_sync: i64 = row.get(1)?;
+            Ok(SyncState {
+                registry: row.get(0)?,
+                last_sync: (last_sync > 0).then_some(last_sync),
+                commit: row.get(2)?,
+                last_attempt: row.get(3)?,
+                last_error: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Returns the saved resume point of an interrupted sync, if any.
+    pub fn get_sync_cursor(&self, registry: &str) -> Result<Option<String>> {
+        let result = self.conn.query_row(
+            "SELECT cursor FROM sync_state WHERE registry = ?",
+            [registry],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(cursor) => Ok(cursor),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_sync_cursor(&self, registry: &str, cursor: Option<&str>) -> Result<()> {
         self.conn.execute(
+            "INSERT INTO sync_state (registry, last_sync, cursor) VALUES (?1, 0, ?2)
+             ON CONFLICT(registry) DO UPDATE SET cursor = ?2",
+            params![registry, cursor],
+        )?;
+        Ok(())
+    }
+
+    pub fn upsert_skill(&self, skill: &Skill) -> Result<i64> {
+        let fp = fingerprint(&skill.skill_md);
+        // last_insert_rowid() isn't updated when the upsert takes the UPDATE path
+        let id = self.conn.query_row(
             r#"
-            INSERT INTO skills (slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            INSERT INTO skills (slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at, content_hash, simhash, first_seen, commit_sha, commit_time, commit_author, permalink)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
             ON CONFLICT(registry, slug) DO UPDATE SET
                 name = excluded.name,
                 description = excluded.description,
@@ -105,7 +481,14 @@ impl Database {
                 version = excluded.version,
                 stars = excluded.stars,
                 trusted = excluded.trusted,
-                updated_at = excluded.updated_at
+                updated_at = excluded.updated_at,
+                content_hash = excluded.content_hash,
+                simhash = excluded.simhash,
+                commit_sha = excluded.commit_sha,
+                commit_time = excluded.commit_time,
+                commit_author = excluded.commit_author,
+                permalink = excluded.permalink
+            RETURNING id
             "#,
             params![
                 skill.slug,
@@ -118,22 +501,102 @@ impl Database {
                 skill.stars,
                 skill.trusted as i64,
                 skill.updated_at,
+                fp.as_ref().map(|f| f.content_hash.as_str()),
+                fp.as_ref().and_then(|f| f.simhash),
+                SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+                skill.commit.as_ref().map(|c| c.sha.as_str()),
+                skill.commit.as_ref().map(|c| c.time),
+                skill.commit.as_ref().map(|c| c.author.as_str()),
+                skill.permalink,
             ],
+            |row| row.get(0),
         )?;
+        Ok(id)
+    }
 
-        let id = self.conn.last_insert_rowid();
-        if id == 0 {
-            let id: i64 = self.conn.query_row(
-                "SELECT id FROM skills WHERE registry = ? AND slug = ?",
-                params![skill.registry, skill.slug],
-                |row| row.get(0),
-            )?;
-            Ok(id)
-        } else {
-            Ok(id)
+    /// Like `upsert_skill`, but leaves the row untouched (including `updated_at`) when
+    /// nothing but the timestamp changed, and reports which case happened.
+    ///
+    /// Stars of an existing row are kept: they come from the metrics sync, not from
+    /// the registry listing.
+    pub fn upsert_skill_tracked(&self, skill: &Skill) -> Result<UpsertOutcome> {
+        match self.get_skill(&skill.registry, &skill.slug)? {
+            None => {
+                self.upsert_skill(skill)?;
+                Ok(UpsertOutcome::Inserted)
+            }
+            Some(existing) => {
+                let same = existing.name == skill.name
+                    && existing.description == skill.description
+                    && existing.skill_md == skill.skill_md
+                    && existing.github_url == skill.github_url
+                    && existing.version == skill.version
+                    && existing.trusted == skill.trusted
+                    && existing.commit == skill.commit;
+                if same {
+                    Ok(UpsertOutcome::Unchanged)
+                } else {
+                    self.upsert_skill(&Skill {
+                        stars: existing.stars,
+                        ..skill.clone()
+                    })?;
+                    Ok(UpsertOutcome::Updated)
+                }
+            }
         }
     }
 
+    /// Deletes the skills of `registry` whose slug is not in `keep`, along with their
+    /// findings and metrics. Returns the removed slugs.
+    pub fn remove_skills_except(&mut self, registry: &str, keep: &HashSet<String>) -> Result<Vec<String>> {
+        let stale: Vec<(i64, String)> = self
+            .get_skills_by_registry(registry)?
+            .into_iter()
+            .filter(|s| !keep.contains(&s.slug))
+            .map(|s| (s.id, s.slug))
+            .collect();
+
+        // A savepoint, so this also works inside `in_transaction`
+        let tx = self.conn.savepoint()?;
+        for (id, _) in &stale {
+            tx.execute("DELETE FROM findings WHERE skill_id = ?", [id])?;
+            tx.execute("DELETE FROM skill_metrics WHERE skill_id = ?", [id])?;
+            tx.execute("UPDATE skills SET canonical_id = NULL WHERE canonical_id = ?", [id])?;
+            tx.execute("DELETE FROM skills WHERE id = ?", [id])?;
+        }
+        tx.commit()?;
+        Ok(stale.into_iter().map(|(_, slug)| slug).collect())
+    }
+
+    pub fn save_sync_run(&self, report: &SyncReport) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO sync_runs (started_at, duration_ms, ok, report) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                report.started_at,
+                report.duration_ms as i64,
+                report.is_ok(),
+                serde_json::to_string(report)?
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Returns the most recent sync runs, newest first.
+    pub fn get_sync_runs(&self, limit: usize) -> Result<Vec<SyncReport>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, report FROM sync_runs ORDER BY id DESC LIMIT ?")?;
+        let rows = stmt.query_map([limit as i64], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        let mut runs = Vec::new();
+        for row in rows {
+            let (id, report) = row?;
+            let mut report: SyncReport = serde_json::from_str(&report)?;
+            report.id = id;
+            runs.push(report);
+        }
+        Ok(runs)
+    }
+
     pub fn update_stars(&self, registry: &str, slug: &str, stars: i64) -> Result<()> {
         self.conn.execute(
             "UPDATE skills SET stars = ? WHERE registry = ? AND slug = ?",
@@ -142,53 +605,70 @@ impl Database {
         Ok(())
     }
 
-    pub fn get_skill(&self, registry: &str, slug: &str) -> Result<Option<Skill>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at 
-             FROM skills WHERE registry = ? AND slug = ? LIMIT 1",
+    /// Records the latest value of a popularity metric for a skill. Unknown skills are ignored.
+    pub fn set_metric(
+        &self,
+        registry: &str,
+        slug: &str,
+        kind: MetricKind,
+        value: i64,
+        source: &str,
+        fetched_at: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO skill_metrics (skill_id, metric, value, source, fetched_at)
+             SELECT id, ?3, ?4, ?5, ?6 FROM skills WHERE registry = ?1 AND slug = ?2
+             ON CONFLICT(skill_id, metric) DO UPDATE SET
+                value = excluded.value, source = excluded.source, fetched_at = excluded.fetched_at",
+            params![registry, slug, kind.as_str(), value, source, fetched_at],
         )?;
-        let result = stmt.query_row(params![registry, slug], |row| {
-            Ok(Skill {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                registry: row.get(3)?,
-                description: row.get(4)?,
-                skill_md: row.get(5)?,
-                github_url: row.get(6)?,
-                version: row.get(7)?,
-                stars: row.get(8)?,
-                trusted: row.get::<_, i64>(9)? != 0,
-                updated_at: row.get(10)?,
-            })
-        });
-        match result {
-            Ok(s) => Ok(Some(s)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
+        Ok(())
+    }
+
+    pub fn get_metrics(&self, skill_id: i64) -> Result<Vec<Metric>> {
+        self.query_metrics("WHERE m.skill_id = ?", [skill_id])
+    }
+
+    pub fn get_all_metrics(&self) -> Result<Vec<Metric>> {
+        self.query_metrics("", [])
+    }
+
+    fn query_metrics(&self, filter: &str, args: impl rusqlite::Params) -> Result<Vec<Metric>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT m.skill_id, s.registry, m.metric, m.value, m.source, m.fetched_at
+             FROM skill_metrics m JOIN skills s ON s.id = m.skill_id {} ORDER BY m.skill_id, m.metric",
+            filter
+        ))?;
+        let rows = stmt.query_map(args, |row| {
+            let kind: String = row.get(2)?;
+            Ok((
+                kind,
+                Metric {
+                    skill_id: row.get(0)?,
+                    registry: row.get(1)?,
+                    kind: MetricKind::Stars,
+                    value: row.get(3)?,
+                    source: row.get(4)?,
+                    fetched_at: row.get(5)?,
+                },
+            ))
+        })?;
+        let mut metrics = Vec::new();
+        for row in rows {
+            let (kind, mut metric) = row?;
+            if let Some(kind) = MetricKind::parse(&kind) {
+                metric.kind = kind;
+                metrics.push(metric);
+            }
         }
+        Ok(metrics)
     }
 
-    pub fn get_skill_by_slug(&self, slug: &str) -> Result<Option<Skill>> {
+    pub fn get_skill(&self, registry: &str, slug: &str) -> Result<Option<Skill>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at 
-             FROM skills WHERE slug = ? LIMIT 1",
+            &format!("SELECT {} FROM skills WHERE registry = ? AND slug = ? LIMIT 1", SKILL_COLUMNS),
         )?;
-        let result = stmt.query_row([slug], |row| {
-            Ok(Skill {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                registry: row.get(3)?,
-                description: row.get(4)?,
-                skill_md: row.get(5)?,
-                github_url: row.get(6)?,
-                version: row.get(7)?,
-                stars: row.get(8)?,
-                trusted: row.get::<_, i64>(9)? != 0,
-                updated_at: row.get(10)?,
-            })
-        });
+        let result = stmt.query_row(params![registry, slug], skill_from_row);
         match result {
             Ok(s) => Ok(Some(s)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -196,25 +676,21 @@ impl Database {
         }
     }
 
+    /// Returns every registry's copy of a slug.
+    pub fn get_skills_by_slug(&self, slug: &str) -> Result<Vec<Skill>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM skills WHERE slug = ? ORDER BY trusted DESC, stars DESC, registry",
+            SKILL_COLUMNS
+        ))?;
+        let rows = stmt.query_map([slug], skill_from_row)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
     pub fn get_all_skills(&self) -> Result<Vec<Skill>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at FROM skills",
+            &format!("SELECT {} FROM skills", SKILL_COLUMNS),
         )?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Skill {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                registry: row.get(3)?,
-                description: row.get(4)?,
-                skill_md: row.get(5)?,
-                github_url: row.get(6)?,
-                version: row.get(7)?,
-                stars: row.get(8)?,
-                trusted: row.get::<_, i64>(9)? != 0,
-                updated_at: row.get(10)?,
-            })
-        })?;
+        let rows = stmt.query_map([], skill_from_row)?;
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
@@ -227,25 +703,183 @@ impl Database {
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    #[allow(dead_code)]
+    /// Returns skills first seen at or after `since`, newest first, with their
+    /// `first_seen` time.
+    pub fn get_skills_first_seen_since(&self, since: i64, registry: Option<&str>) -> Result<Vec<(Skill, i64)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, first_seen FROM skills
+             WHERE first_seen >= ?1 AND (?2 IS NULL OR registry = ?2)
+             ORDER BY first_seen DESC, id DESC",
+            SKILL_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![since, registry], |row| Ok((skill_from_row(row)?, row.get(15)?)))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
     pub fn get_skills_by_registry(&self, registry: &str) -> Result<Vec<Skill>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, slug, name, registry, description, skill_md, github_url, version, stars, trusted, updated_at 
-             FROM skills WHERE registry = ?",
+            &format!("SELECT {} FROM skills WHERE registry = ?", SKILL_COLUMNS),
+        )?;
+        let rows = stmt.query_map([registry], skill_from_row)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn get_fingerprints(&self) -> Result<Vec<FingerprintRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, registry, slug, name, trusted, stars, content_hash, simhash, canonical_id FROM skills ORDER BY id",
         )?;
-        let rows = stmt.query_map([registry], |row| {
-            Ok(Skill {
+        let rows = stmt.query_map([], |row| {
+            Ok(FingerprintRow {
                 id: row.get(0)?,
+                registry: row.get(1)?,
+                slug: row.get(2)?,
+                name: row.get(3)?,
+                trusted: row.get::<_, i64>(4)? != 0,
+                stars: row.get(5)?,
+                content_hash: row.get(6)?,
+                simhash: row.get(7)?,
+                canonical_id: row.get(8)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn set_canonical_ids(&self, assignments: &[(i64, Option<i64>)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("UPDATE skills SET canonical_id = ? WHERE id = ?")?;
+            for (id, canonical_id) in assignments {
+                stmt.execute(params![canonical_id, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the canonical copy if the given skill is a duplicate of another one.
+    pub fn get_canonical_skill(&self, id: i64) -> Result<Option<Skill>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM skills WHERE id = (SELECT canonical_id FROM skills WHERE id = ?)",
+            SKILL_COLUMNS
+        ))?;
+        match stmt.query_row([id], skill_from_row) {
+            Ok(s) => Ok(Some(s)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the other copies of a skill, excluding the skill itself.
+    pub fn get_duplicate_skills(&self, id: i64) -> Result<Vec<Skill>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM skills
+             WHERE id != ?1 AND COALESCE(canonical_id, id) =
+                 (SELECT COALESCE(canonical_id, id) FROM skills WHERE id = ?1)
+             ORDER BY trusted DESC, registry",
+            SKILL_COLUMNS
+        ))?;
+        let rows = stmt.query_map([id], skill_from_row)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Replaces all findings of one kind with a freshly computed set.
+    pub fn replace_findings(&self, kind: &str, findings: &[Finding]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM findings WHERE kind = ?", [kind])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO findings (skill_id, kind, severity, detail, created_at) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            for f in findings {
+                stmt.execute(params![f.skill_id, f.kind, f.severity, f.detail, f.created_at])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_findings(&self, skill_id: i64) -> Result<Vec<Finding>> {
+        self.query_findings("WHERE skill_id = ?1", [skill_id])
+    }
+
+    pub fn get_all_findings(&self) -> Result<Vec<Finding>> {
+        self.query_findings("", [])
+    }
+
+    fn query_findings(&self, filter: &str, args: impl rusqlite::Params) -> Result<Vec<Finding>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT skill_id, kind, severity, detail, created_at FROM findings {} ORDER BY id",
+            filter
+        ))?;
+        let rows = stmt.query_map(args, |row| {
+            Ok(Finding {
+                skill_id: row.get(0)?,
+                kind: row.get(1)?,
+                severity: row.get(2)?,
+                detail: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn replace_semantic_model<'a>(
+        &self,
+        terms: impl Iterator<Item = &'a TermVector>,
+        docs: &[DocVector],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM semantic_terms", [])?;
+        tx.execute("DELETE FROM semantic_docs", [])?;
+        {
+            let mut stmt = tx.prepare("INSERT INTO semantic_terms (term, idf, vector) VALUES (?, ?, ?)")?;
+            for t in terms {
+                stmt.execute(params![t.term, t.idf, vector_to_blob(&t.vector)])?;
+            }
+            let mut stmt = tx.prepare(
+                "INSERT INTO semantic_docs (registry, slug, name, description, vector) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            for d in docs {
+                stmt.execute(params![d.registry, d.slug, d.name, d.description, vector_to_blob(&d.vector)])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_semantic_terms(&self, terms: &[String]) -> Result<Vec<TermVector>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT term, idf, vector FROM semantic_terms WHERE term = ?")?;
+        let mut result = Vec::new();
+        for term in terms {
+            let row = stmt.query_row([term], |row| {
+                Ok(TermVector {
+                    term: row.get(0)?,
+                    idf: row.get(1)?,
+                    vector: blob_to_vector(&row.get::<_, Vec<u8>>(2)?),
+                })
+            });
+            match row {
+                Ok(t) => result.push(t),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn get_semantic_docs(&self) -> Result<Vec<DocVector>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT registry, slug, name, description, vector FROM semantic_docs")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DocVector {
+                registry: row.get(0)?,
                 slug: row.get(1)?,
                 name: row.get(2)?,
-                registry: row.get(3)?,
-                description: row.get(4)?,
-                skill_md: row.get(5)?,
-                github_url: row.get(6)?,
-                version: row.get(7)?,
-                stars: row.get(8)?,
-                trusted: row.get::<_, i64>(9)? != 0,
-                updated_at: row.get(10)?,
+                description: row.get(3)?,
+                vector: blob_to_vector(&row.get::<_, Vec<u8>>(4)?),
             })
         })?;
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
@@ -270,6 +904,8 @@ mod tests {
             stars: 0,
             trusted,
             updated_at: 1234567890,
+            commit: None,
+            permalink: None,
         }
     }
 
@@ -317,7 +953,75 @@ mod tests {
     }
 
     #[test]
-    fn test_get_skill_by_slug() {
+    fn test_upsert_returns_id_of_updated_row() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+
+        let first = db.upsert_skill(&create_test_skill("first", "anthropic", true)).unwrap();
+        let second = db.upsert_skill(&create_test_skill("second", "anthropic", true)).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(db.upsert_skill(&create_test_skill("first", "anthropic", true)).unwrap(), first);
+    }
+
+    #[test]
+    fn test_in_transaction_rolls_back_on_error() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+
+        let result: Result<()> = db.in_transaction(|db| {
+            db.upsert_skill(&create_test_skill("pdf", "anthropic", true))?;
+            db.remove_skills_except("anthropic", &HashSet::new())?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(result.is_err());
+        assert!(db.get_skill("anthropic", "pdf").unwrap().is_none());
+
+        db.in_transaction(|db| db.upsert_skill(&create_test_skill("pdf", "anthropic", true)))
+            .unwrap();
+        assert!(db.get_skill("anthropic", "pdf").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_import_catalog() {
+        let dir = tempdir().unwrap();
+        let source_path = dir.path().join("source.db");
+        let source = Database::open(&source_path).unwrap();
+        let id = source.upsert_skill(&create_test_skill("pdf", "anthropic", true)).unwrap();
+        source.set_metric("anthropic", "pdf", MetricKind::Stars, 7, "github", 100).unwrap();
+        source.set_last_sync("anthropic", 1234, None).unwrap();
+        source.set_sync_cursor("anthropic", Some("page-3")).unwrap();
+        drop(source);
+
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("stale", "clawdhub", false)).unwrap();
+        assert_eq!(db.import_catalog(&source_path).unwrap(), 1);
+
+        assert!(db.get_skill("clawdhub", "stale").unwrap().is_none());
+        let skill = db.get_skill("anthropic", "pdf").unwrap().unwrap();
+        assert_eq!(skill.id, id);
+        assert_eq!(db.get_metrics(id).unwrap()[0].value, 7);
+        assert_eq!(db.get_sync_states().unwrap()[0].last_sync, Some(1234));
+        assert!(db.get_sync_cursor("anthropic").unwrap().is_none());
+
+        // Snapshots from before a column existed still load
+        let old_path = dir.path().join("old.db");
+        Connection::open(&old_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE skills (id INTEGER PRIMARY KEY, slug TEXT, name TEXT, registry TEXT, github_url TEXT);
+                 INSERT INTO skills VALUES (1, 'web', 'Web', 'openai', 'https://github.com/openai/web');",
+            )
+            .unwrap();
+        assert_eq!(db.import_catalog(&old_path).unwrap(), 1);
+        assert_eq!(db.get_skill("openai", "web").unwrap().unwrap().description, "");
+
+        std::fs::write(dir.path().join("empty.db"), "").unwrap();
+        assert!(db.import_catalog(&dir.path().join("empty.db")).is_err());
+        assert!(db.get_skill("openai", "web").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_get_skills_by_slug() {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         let db = Database::open(&db_path).unwrap();
@@ -325,19 +1029,37 @@ mod tests {
         let skill = create_test_skill("slug-test", "openai", true);
         db.upsert_skill(&skill).unwrap();
 
-        let retrieved = db.get_skill_by_slug("slug-test").unwrap().unwrap();
-        assert_eq!(retrieved.slug, "slug-test");
-        assert!(retrieved.trusted);
+        let retrieved = db.get_skills_by_slug("slug-test").unwrap();
+        assert_eq!(retrieved.len(), 1);
+        assert_eq!(retrieved[0].slug, "slug-test");
+        assert!(retrieved[0].trusted);
+    }
+
+    #[test]
+    fn test_get_skills_by_slug_not_found() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::open(&db_path).unwrap();
+
+        let result = db.get_skills_by_slug("nonexistent").unwrap();
+        assert!(result.is_empty());
     }
 
     #[test]
-    fn test_get_skill_by_slug_not_found() {
+    fn test_get_skills_by_slug_puts_trusted_first() {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         let db = Database::open(&db_path).unwrap();
 
-        let result = db.get_skill_by_slug("nonexistent").unwrap();
-        assert!(result.is_none());
+        let mut community = create_test_skill("pdf", "clawdhub", false);
+        community.stars = 500;
+        db.upsert_skill(&community).unwrap();
+        db.upsert_skill(&create_test_skill("pdf", "anthropic", true)).unwrap();
+
+        let all = db.get_skills_by_slug("pdf").unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].registry, "anthropic");
+        assert_eq!(all[1].registry, "clawdhub");
     }
 
     #[test]
@@ -386,6 +1108,56 @@ mod tests {
         assert_eq!(retrieved.stars, 42);
     }
 
+    #[test]
+    fn test_metrics_are_kept_separate() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::open(&db_path).unwrap();
+
+        let id = db.upsert_skill(&create_test_skill("metrics-test", "skillssh", false)).unwrap();
+        db.set_metric("skillssh", "metrics-test", MetricKind::Installs, 1200, "skills.sh", 100).unwrap();
+        db.set_metric("skillssh", "metrics-test", MetricKind::Installs, 1300, "skills.sh", 200).unwrap();
+        db.set_metric("skillssh", "unknown", MetricKind::Installs, 5, "skills.sh", 200).unwrap();
+
+        let metrics = db.get_metrics(id).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].kind, MetricKind::Installs);
+        assert_eq!(metrics[0].value, 1300);
+        assert_eq!(metrics[0].fetched_at, 200);
+        assert_eq!(db.get_all_metrics().unwrap().len(), 1);
+
+        // Installs never leak into the stars column
+        assert_eq!(db.get_skill("skillssh", "metrics-test").unwrap().unwrap().stars, 0);
+    }
+
+    #[test]
+    fn test_legacy_skillssh_stars_become_installs() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::open(&db_path).unwrap();
+        let mut legacy = create_test_skill("legacy", "skillssh", false);
+        legacy.stars = 900;
+        let id = db.upsert_skill(&legacy).unwrap();
+        let mut starred = create_test_skill("starred", "clawdhub", false);
+        starred.stars = 12;
+        db.upsert_skill(&starred).unwrap();
+        drop(db);
+
+        let db = Database::open(&db_path).unwrap();
+        assert_eq!(db.get_skill("skillssh", "legacy").unwrap().unwrap().stars, 0);
+        let metrics = db.get_metrics(id).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].kind, MetricKind::Installs);
+        assert_eq!(metrics[0].value, 900);
+        assert_eq!(db.get_skill("clawdhub", "starred").unwrap().unwrap().stars, 12);
+
+        // A later sync's install count isn't overwritten on the next open
+        db.set_metric("skillssh", "legacy", MetricKind::Installs, 950, "https://skills.sh", 2000000000).unwrap();
+        drop(db);
+        let db = Database::open(&db_path).unwrap();
+        assert_eq!(db.get_metrics(id).unwrap()[0].value, 950);
+    }
+
     #[test]
     fn test_needs_initial_sync() {
         let dir = tempdir().unwrap();
@@ -406,12 +1178,86 @@ mod tests {
         let db = Database::open(&db_path).unwrap();
 
         assert!(db.get_last_sync("clawdhub").unwrap().is_none());
+        assert!(db.get_sync_states().unwrap().is_empty());
 
         db.set_last_sync("clawdhub", 1234567890, Some("etag123")).unwrap();
 
         let (timestamp, etag) = db.get_last_sync("clawdhub").unwrap().unwrap();
         assert_eq!(timestamp, 1234567890);
         assert_eq!(etag, Some("etag123".to_string()));
+
+        db.set_sync_attempt("clawdhub", 1234567890, None).unwrap();
+
+        db.set_sync_commit("jo", Some("abc123")).unwrap();
+        db.set_sync_attempt("jo", 1234567999, Some("fetch failed")).unwrap();
+        let states = db.get_sync_states().unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].last_sync, Some(1234567890));
+        assert_eq!(states[0].last_attempt, Some(1234567890));
+        assert_eq!(states[0].last_error, None);
+        assert_eq!(states[1].registry, "jo");
+        assert_eq!(states[1].last_sync, None);
+        assert_eq!(states[1].commit.as_deref(), Some("abc123"));
+        assert_eq!(states[1].last_attempt, Some(1234567999));
+        assert_eq!(states[1].last_error.as_deref(), Some("fetch failed"));
+
+        // The next attempt replaces the error
+        db.set_sync_attempt("jo", 1234568000, None).unwrap();
+        assert_eq!(db.get_sync_states().unwrap()[1].last_error, None);
+        // An attempt alone doesn't count as a completed sync
+        assert!(db.get_last_sync("jo").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_count_skills_by_registry() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("pdf", "anthropic", true)).unwrap();
+        db.upsert_skill(&create_test_skill("docx", "anthropic", true)).unwrap();
+        db.upsert_skill(&create_test_skill("trello", "clawdhub", false)).unwrap();
+
+        let counts = db.count_skills_by_registry().unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["anthropic"], 2);
+        assert_eq!(counts["clawdhub"], 1);
+    }
+
+    #[test]
+    fn test_upsert_skill_tracked() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::open(&db_path).unwrap();
+
+        let mut skill = create_test_skill("tracked", "clawdhub", false);
+        assert_eq!(db.upsert_skill_tracked(&skill).unwrap(), UpsertOutcome::Inserted);
+
+        skill.updated_at += 100;
+        assert_eq!(db.upsert_skill_tracked(&skill).unwrap(), UpsertOutcome::Unchanged);
+        assert_eq!(db.get_skill("clawdhub", "tracked").unwrap().unwrap().updated_at, 1234567890);
+
+        db.update_stars("clawdhub", "tracked", 9).unwrap();
+        skill.description = "Changed".to_string();
+        assert_eq!(db.upsert_skill_tracked(&skill).unwrap(), UpsertOutcome::Updated);
+        assert_eq!(db.get_skill("clawdhub", "tracked").unwrap().unwrap().stars, 9);
+    }
+
+    #[test]
+    fn test_sync_cursor() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::open(&db_path).unwrap();
+
+        assert!(db.get_sync_cursor("skillssh").unwrap().is_none());
+        db.set_sync_cursor("skillssh", Some("resume-here")).unwrap();
+        assert_eq!(db.get_sync_cursor("skillssh").unwrap().as_deref(), Some("resume-here"));
+        // A cursor alone doesn't count as a completed sync
+        assert!(db.get_last_sync("skillssh").unwrap().is_none());
+
+        db.set_last_sync("skillssh", 1234567890, None).unwrap();
+        assert_eq!(db.get_sync_cursor("skillssh").unwrap().as_deref(), Some("resume-here"));
+
+        db.set_sync_cursor("skillssh", None).unwrap();
+        assert!(db.get_sync_cursor("skillssh").unwrap().is_none());
     }
 
     #[test]
@@ -427,6 +1273,60 @@ mod tests {
 
         assert!(db.get_last_sync("clawdhub").unwrap().is_none());
         assert!(db.get_last_sync("anthropic").unwrap().is_none());
+        assert!(db.get_sync_states().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_commit_info_roundtrip() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        let mut skill = create_test_skill("pdf", "anthropic", true);
+        db.upsert_skill(&skill).unwrap();
+        assert!(db.get_skill("anthropic", "pdf").unwrap().unwrap().commit.is_none());
+
+        // A new upstream commit counts as an update even if SKILL.md is unchanged
+        skill.commit = Some(CommitInfo {
+            sha: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            time: 1700000000,
+            author: "Jane".to_string(),
+        });
+        assert_eq!(db.upsert_skill_tracked(&skill).unwrap(), UpsertOutcome::Updated);
+        assert_eq!(db.get_skill("anthropic", "pdf").unwrap().unwrap().commit, skill.commit);
+    }
+
+    #[test]
+    fn test_first_seen_survives_updates() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("pdf", "anthropic", true)).unwrap();
+        db.upsert_skill(&create_test_skill("calendar", "clawdhub", false)).unwrap();
+        db.conn.execute("UPDATE skills SET first_seen = 100 WHERE slug = 'pdf'", []).unwrap();
+
+        let mut updated = create_test_skill("pdf", "anthropic", true);
+        updated.description = "Changed".to_string();
+        db.upsert_skill(&updated).unwrap();
+
+        let recent = db.get_skills_first_seen_since(1000, None).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].0.slug, "calendar");
+        assert_eq!(db.get_skills_first_seen_since(0, Some("anthropic")).unwrap()[0].1, 100);
+    }
+
+    #[test]
+    fn test_http_cache() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        let url = "https://clawhub.com/api/v1/skills?limit=100";
+
+        db.set_http_cache(url, &CachedResponse { etag: "\"v1\"".to_string(), body: "{}".to_string() }).unwrap();
+        db.set_http_cache(url, &CachedResponse { etag: "\"v2\"".to_string(), body: "[]".to_string() }).unwrap();
+        let cache = db.get_http_cache().unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache[url].etag, "\"v2\"");
+
+        // A forced resync drops cached responses too
+        db.clear_sync_state().unwrap();
+        assert!(db.get_http_cache().unwrap().is_empty());
     }
 
     #[test]
@@ -0,0 +1,244 @@
+use crate::db::Database;
+use crate::quality::QualityScores;
+use crate::semantic::fnv1a;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Maximum simhash Hamming distance for two SKILL.md files to count as near-duplicates.
+const MAX_HAMMING_DISTANCE: u32 = 3;
+
+/// Number of words per shingle fed into the simhash.
+const SHINGLE_SIZE: usize = 3;
+
+/// Documents with fewer shingles than this are too short for a meaningful simhash.
+const MIN_SHINGLES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub content_hash: String,
+    pub simhash: Option<i64>,
+}
+
+/// Per-skill data needed to cluster copies and choose a canonical entry.
+#[derive(Debug, Clone)]
+pub struct FingerprintRow {
+    pub id: i64,
+    pub registry: String,
+    pub slug: String,
+    pub name: String,
+    pub trusted: bool,
+    pub stars: i64,
+    pub content_hash: Option<String>,
+    pub simhash: Option<i64>,
+    pub canonical_id: Option<i64>,
+}
+
+/// Computes the exact content hash and near-duplicate simhash of a SKILL.md.
+///
+/// Returns `None` for empty content (e.g. registries that only list metadata).
+pub fn fingerprint(skill_md: &str) -> Option<Fingerprint> {
+    let words: Vec<String> = skill_md
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    // Hash the whitespace-normalized text so CRLF/indentation changes don't matter
+    let normalized = words.join(" ");
+    let digest = Sha256::digest(normalized.as_bytes());
+    let content_hash = digest.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let shingles: Vec<u64> = words
+        .windows(SHINGLE_SIZE.min(words.len()))
+        .map(|w| fnv1a(w.join(" ").as_bytes()))
+        .collect();
+    let simhash = (shingles.len() >= MIN_SHINGLES).then(|| simhash(&shingles) as i64);
+
+    Some(Fingerprint { content_hash, simhash })
+}
+
+fn simhash(features: &[u64]) -> u64 {
+    let mut weights = [0i32; 64];
+    for f in features {
+        for (bit, w) in weights.iter_mut().enumerate() {
+            if f >> bit & 1 == 1 {
+                *w += 1;
+            } else {
+                *w -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0u64, |acc, (bit, _)| acc | 1 << bit)
+}
+
+/// Groups copies of the same skill across registries and records a canonical entry
+/// for each group. Returns the number of skills marked as duplicates.
+///
+/// The canonical copy is the one from a trusted registry, then the one with the
+/// highest quality score, then the most starred.
+pub fn cluster_duplicates(db: &Database, quality_scores: &QualityScores) -> Result<usize> {
+    let rows = db.get_fingerprints()?;
+    let clusters = cluster(&rows);
+
+    let mut assignments = Vec::with_capacity(rows.len());
+    let mut duplicates = 0;
+    for members in clusters {
+        let canonical = members
+            .iter()
+            .map(|&i| &rows[i])
+            .max_by_key(|r| {
+                let quality = quality_scores
+                    .get_score(&r.registry, &r.slug)
+                    .or_else(|| quality_scores.get_score(&r.registry, &r.name))
+                    .unwrap_or(0);
+                (r.trusted, quality, r.stars, std::cmp::Reverse(r.id))
+            })
+            .map(|r| r.id)
+            .unwrap_or_default();
+
+        for &i in &members {
+            let id = rows[i].id;
+            if id == canonical || members.len() == 1 {
+                assignments.push((id, None));
+            } else {
+                assignments.push((id, Some(canonical)));
+                duplicates += 1;
+            }
+        }
+    }
+
+    db.set_canonical_ids(&assignments)?;
+    tracing::info!("Marked {} skills as duplicates of a canonical copy", duplicates);
+    Ok(duplicates)
+}
+
+/// Union-find over exact hash matches and near-duplicate simhashes.
+fn cluster(rows: &[FingerprintRow]) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..rows.len()).collect();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut by_hash: HashMap<&str, usize> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        if let Some(hash) = row.content_hash.as_deref() {
+            if let Some(&first) = by_hash.get(hash) {
+                let (a, b) = (find(&mut parent, first), find(&mut parent, i));
+                parent[a] = b;
+            } else {
+                by_hash.insert(hash, i);
+            }
+        }
+    }
+
+    let hashed: Vec<(usize, u64)> = rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| r.simhash.map(|h| (i, h as u64)))
+        .collect();
+    for (n, &(i, a)) in hashed.iter().enumerate() {
+        for &(j, b) in &hashed[n + 1..] {
+            if (a ^ b).count_ones() <= MAX_HAMMING_DISTANCE {
+                let (ra, rb) = (find(&mut parent, i), find(&mut parent, j));
+                parent[ra] = rb;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..rows.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+    groups.sort();
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Skill;
+    use tempfile::tempdir;
+
+    const BODY: &str = "# PDF\n\nExtract text and tables from PDF files, fill forms, merge and split \
+        documents. Use pdfplumber for extraction and pypdf for manipulation. Always check the page \
+        count before processing large files and report errors clearly to the user.";
+
+    fn create_test_skill(slug: &str, registry: &str, trusted: bool, skill_md: &str) -> Skill {
+        Skill {
+            id: 0,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            registry: registry.to_string(),
+            description: String::new(),
+            skill_md: skill_md.to_string(),
+            github_url: format!("https://github.com/test/{}", slug),
+            version: None,
+            stars: 0,
+            trusted,
+            updated_at: 1234567890,
+            commit: None,
+            permalink: None,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_whitespace() {
+        let a = fingerprint("# Title\r\n\r\nSome   content here").unwrap();
+        let b = fingerprint("# Title\n\nSome content here\n").unwrap();
+        assert_eq!(a.content_hash, b.content_hash);
+        assert!(fingerprint("   ").is_none());
+    }
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let a = fingerprint(BODY).unwrap().simhash.unwrap() as u64;
+        let edited = BODY.replace("report errors clearly", "report errors");
+        let b = fingerprint(&edited).unwrap().simhash.unwrap() as u64;
+        let other = fingerprint(&"Completely unrelated calendar scheduling instructions ".repeat(8))
+            .unwrap()
+            .simhash
+            .unwrap() as u64;
+
+        assert!((a ^ b).count_ones() < (a ^ other).count_ones());
+    }
+
+    #[test]
+    fn test_short_documents_have_no_simhash() {
+        assert!(fingerprint("# Tiny").unwrap().simhash.is_none());
+    }
+
+    #[test]
+    fn test_cluster_duplicates_prefers_trusted() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("pdf-copy", "clawdhub", false, BODY)).unwrap();
+        let canonical = db.upsert_skill(&create_test_skill("pdf", "anthropic", true, BODY)).unwrap();
+        db.upsert_skill(&create_test_skill("other", "clawdhub", false, "# Other\n\nUnrelated")).unwrap();
+
+        let duplicates = cluster_duplicates(&db, &QualityScores::load()).unwrap();
+        assert_eq!(duplicates, 1);
+
+        let copy = db.get_skill("clawdhub", "pdf-copy").unwrap().unwrap();
+        let resolved = db.get_canonical_skill(copy.id).unwrap().unwrap();
+        assert_eq!(resolved.id, canonical);
+        assert!(db.get_canonical_skill(canonical).unwrap().is_none());
+
+        let copies = db.get_duplicate_skills(canonical).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].registry, "clawdhub");
+    }
+}
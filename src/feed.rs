@@ -0,0 +1,203 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+
+const FEED_TITLE: &str = "New skills";
+const FEED_ID: &str = "urn:safe-skill-search:new";
+
+/// One skill in a what's-new feed.
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub title: String,
+    pub link: String,
+    pub summary: String,
+    pub registry: String,
+    pub slug: String,
+    pub first_seen: i64,
+}
+
+impl FeedEntry {
+    /// A stable ID per skill: links aren't unique, e.g. skills.sh entries synced without
+    /// a checkout all link to their source repository.
+    fn id(&self) -> String {
+        format!("urn:safe-skill-search:{}:{}", self.registry, self.slug)
+    }
+}
+
+/// Parses a time window (`--since`, `--updated-since`) into a unix timestamp.
+///
+/// Accepts a relative age (`30m`, `12h`, `7d`, `2w`, `1y`), a date (`2026-01-31`), an
+/// RFC 3339 time, or `last-sync` for the start of the most recent sync.
+pub fn parse_since(spec: &str, now: i64, last_sync: Option<i64>) -> Result<i64> {
+    let spec = spec.trim();
+    if spec == "last-sync" {
+        return last_sync.ok_or_else(|| anyhow::anyhow!("no sync has been recorded yet"));
+    }
+
+    if let Some(age) = relative_age(spec) {
+        return now.checked_sub(age?).ok_or_else(|| anyhow::anyhow!("time '{}' is too large", spec));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp());
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(time.timestamp());
+    }
+    anyhow::bail!("invalid time '{}': expected e.g. 7d, 2026-01-31 or last-sync", spec)
+}
+
+/// Parses a relative age (`30m`, `12h`, `7d`, `2w`, `1y`) into seconds.
+pub fn parse_age(spec: &str) -> Result<i64> {
+    let spec = spec.trim();
+    relative_age(spec).unwrap_or_else(|| anyhow::bail!("invalid age '{}': expected e.g. 12h, 7d or 2w", spec))
+}
+
+/// `None` when `spec` isn't a number followed by a unit.
+fn relative_age(spec: &str) -> Option<Result<i64>> {
+    let unit = spec.chars().last().filter(|c| c.is_ascii_alphabetic())?;
+    let n = spec[..spec.len() - 1].parse::<i64>().ok()?;
+    let seconds = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        'y' => 365 * 86400,
+        _ => return Some(Err(anyhow::anyhow!("unknown time unit '{}' in '{}' (use m, h, d, w or y)", unit, spec))),
+    };
+    Some(n.checked_mul(seconds).ok_or_else(|| anyhow::anyhow!("time '{}' is too large", spec)))
+}
+
+/// Renders entries as an Atom 1.0 feed.
+pub fn render_atom(entries: &[FeedEntry], now: i64) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", FEED_TITLE));
+    xml.push_str(&format!("  <id>{}</id>\n", FEED_ID));
+    xml.push_str(&format!("  <updated>{}</updated>\n", rfc3339(now)));
+    // Entries have no author of their own, so the feed needs one
+    xml.push_str("  <author><name>safe-skill-search</name></author>\n");
+    for e in entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&e.title)));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", escape(&e.link)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape(&e.id())));
+        xml.push_str(&format!("    <updated>{}</updated>\n", rfc3339(e.first_seen)));
+        xml.push_str(&format!("    <category term=\"{}\"/>\n", escape(&e.registry)));
+        xml.push_str(&format!("    <summary>{}</summary>\n", escape(&e.summary)));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Renders entries as an RSS 2.0 feed.
+pub fn render_rss(entries: &[FeedEntry], now: i64) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&format!("  <title>{}</title>\n", FEED_TITLE));
+    xml.push_str("  <description>Skills first seen by safe-skill-search</description>\n");
+    xml.push_str("  <link>https://github.com/jo-inc/safe-skill-search</link>\n");
+    xml.push_str(&format!("  <lastBuildDate>{}</lastBuildDate>\n", rfc2822(now)));
+    for e in entries {
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&e.title)));
+        xml.push_str(&format!("    <link>{}</link>\n", escape(&e.link)));
+        xml.push_str(&format!("    <guid isPermaLink=\"false\">{}</guid>\n", escape(&e.id())));
+        xml.push_str(&format!("    <pubDate>{}</pubDate>\n", rfc2822(e.first_seen)));
+        xml.push_str(&format!("    <category>{}</category>\n", escape(&e.registry)));
+        xml.push_str(&format!("    <description>{}</description>\n", escape(&e.summary)));
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn to_datetime(timestamp: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp, 0).unwrap_or_default()
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    to_datetime(timestamp).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+fn rfc2822(timestamp: i64) -> String {
+    to_datetime(timestamp).to_rfc2822()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1770000000; // 2026-02-02T02:40:00Z
+
+    fn entry() -> FeedEntry {
+        FeedEntry {
+            title: "Q&A <helper>".to_string(),
+            link: "https://github.com/test/qa?x=1&y=2".to_string(),
+            summary: "Answers questions".to_string(),
+            registry: "clawdhub".to_string(),
+            slug: "qa".to_string(),
+            first_seen: NOW,
+        }
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("7d", NOW, None).unwrap(), NOW - 7 * 86400);
+        assert_eq!(parse_since("12h", NOW, None).unwrap(), NOW - 12 * 3600);
+        assert_eq!(parse_since("2026-02-01", NOW, None).unwrap(), 1769904000);
+        assert_eq!(parse_since("2026-02-01T00:00:00+01:00", NOW, None).unwrap(), 1769900400);
+        assert_eq!(parse_since("last-sync", NOW, Some(42)).unwrap(), 42);
+        assert!(parse_since("last-sync", NOW, None).is_err());
+        assert_eq!(parse_since("1y", NOW, None).unwrap(), NOW - 365 * 86400);
+        assert!(parse_since("7x", NOW, None).is_err());
+        assert!(parse_since("yesterday", NOW, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("36h").unwrap(), 36 * 3600);
+        assert_eq!(parse_age(" 2w ").unwrap(), 14 * 86400);
+        assert!(parse_age("7x").is_err());
+        assert!(parse_age("2026-02-01").is_err());
+        assert!(parse_age("last-sync").is_err());
+        assert!(parse_age("99999999999999999y").is_err());
+        assert!(parse_since("99999999999999999y", NOW, None).is_err());
+        assert!(parse_since("-153722867280912930m", NOW, None).is_err());
+    }
+
+    #[test]
+    fn test_render_atom_escapes_entries() {
+        let xml = render_atom(&[entry()], NOW);
+        assert!(xml.contains("<title>Q&amp;A &lt;helper&gt;</title>"));
+        assert!(xml.contains("href=\"https://github.com/test/qa?x=1&amp;y=2\""));
+        assert!(xml.contains("<updated>2026-02-02T02:40:00Z</updated>"));
+        assert!(xml.contains("<id>urn:safe-skill-search:clawdhub:qa</id>"));
+        assert!(xml.contains("<author><name>safe-skill-search</name></author>"));
+    }
+
+    #[test]
+    fn test_render_rss() {
+        let xml = render_rss(&[entry()], NOW);
+        assert!(xml.contains("<pubDate>Mon, 2 Feb 2026 02:40:00 +0000</pubDate>"));
+        assert!(xml.contains("<category>clawdhub</category>"));
+        assert!(xml.contains("<guid isPermaLink=\"false\">urn:safe-skill-search:clawdhub:qa</guid>"));
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+/// The code hosting software behind a repository, which decides its web URL layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl Forge {
+    /// Guesses the forge from a host name; self-hosted instances with neutral names
+    /// need an explicit `forge` in the registry config.
+    pub fn detect(host: &str) -> Self {
+        let host = host.to_lowercase();
+        if host.contains("gitlab") {
+            Self::GitLab
+        } else if host.contains("gitea") || host.contains("forgejo") || host == "codeberg.org" {
+            Self::Gitea
+        } else {
+            Self::GitHub
+        }
+    }
+}
+
+/// A revision to link to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rev<'a> {
+    Branch(&'a str),
+    Commit(&'a str),
+}
+
+/// Where a repository lives on the web, derived from its clone URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoLocation {
+    pub forge: Forge,
+    /// `https://host[:port]`
+    pub base: String,
+    /// `owner/repo`, or `group/subgroup/repo` on GitLab
+    pub path: String,
+}
+
+impl RepoLocation {
+    /// Parses an `https://`, `ssh://` or scp-style (`git@host:owner/repo.git`) clone URL.
+    pub fn parse(repo_url: &str, forge: Option<Forge>) -> Option<Self> {
+        let url = repo_url.trim().trim_end_matches('/');
+        let (host, path) = if let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+            rest.split_once('/')?
+        } else if let Some(rest) = url.strip_prefix("ssh://") {
+            let (host, path) = rest.split_once('/')?;
+            // The SSH port is not the web port
+            let host = host.rsplit('@').next()?;
+            (host.split(':').next()?, path)
+        } else if url.contains("://") {
+            // file:// and other schemes have no web page
+            return None;
+        } else if let Some((user_host, path)) = url.split_once(':').filter(|(user_host, _)| !user_host.contains('/')) {
+            (user_host.rsplit('@').next()?, path)
+        } else {
+            return None;
+        };
+
+        let host = host.rsplit('@').next()?;
+        let path = path.trim_end_matches(".git").trim_matches('/');
+        if host.is_empty() || !path.contains('/') {
+            return None;
+        }
+        Some(Self {
+            forge: forge.unwrap_or_else(|| Forge::detect(host.split(':').next().unwrap_or(host))),
+            base: format!("https://{}", host),
+            path: path.to_string(),
+        })
+    }
+
+    pub fn repo_url(&self) -> String {
+        format!("{}/{}", self.base, self.path)
+    }
+
+    /// Web URL of a directory at a branch or commit.
+    pub fn tree_url(&self, rev: Rev, dir: &str) -> String {
+        let dir = dir.trim_matches('/');
+        let url = match (self.forge, rev) {
+            (Forge::GitHub, Rev::Branch(r) | Rev::Commit(r)) => format!("{}/tree/{}", self.repo_url(), r),
+            (Forge::GitLab, Rev::Branch(r) | Rev::Commit(r)) => format!("{}/-/tree/{}", self.repo_url(), r),
+            (Forge::Gitea, Rev::Branch(r)) => format!("{}/src/branch/{}", self.repo_url(), r),
+            (Forge::Gitea, Rev::Commit(r)) => format!("{}/src/commit/{}", self.repo_url(), r),
+        };
+        if dir.is_empty() {
+            url
+        } else {
+            format!("{}/{}", url, dir)
+        }
+    }
+}
+
+/// Links into one clone: browsable URLs on its branch and permalinks pinned to the
+/// commit that was scanned.
+#[derive(Debug, Clone)]
+pub struct RepoLinks {
+    pub location: Option<RepoLocation>,
+    pub repo_url: String,
+    pub branch: String,
+    pub head: Option<String>,
+}
+
+impl RepoLinks {
+    pub fn new(repo_url: &str, forge: Option<Forge>, branch: &str, head: Option<&str>) -> Self {
+        Self {
+            location: RepoLocation::parse(repo_url, forge),
+            repo_url: repo_url.trim_end_matches(".git").to_string(),
+            branch: branch.to_string(),
+            head: head.map(str::to_string),
+        }
+    }
+
+    /// Links to files on disk, for registries that aren't git repositories.
+    pub fn local(root: &std::path::Path) -> Self {
+        Self {
+            location: None,
+            repo_url: format!("file://{}", root.display()),
+            branch: String::new(),
+            head: None,
+        }
+    }
+
+    /// URL of a directory (relative to the repo root) on the clone's branch.
+    pub fn tree_url(&self, dir: &std::path::Path) -> String {
+        match &self.location {
+            Some(location) => location.tree_url(Rev::Branch(&self.branch), &url_path(dir)),
+            None => format!("{}/{}", self.repo_url, url_path(dir)),
+        }
+    }
+
+    /// URL of a directory at the exact commit that was scanned.
+    pub fn permalink(&self, dir: &std::path::Path) -> Option<String> {
+        let location = self.location.as_ref()?;
+        Some(location.tree_url(Rev::Commit(self.head.as_deref()?), &url_path(dir)))
+    }
+}
+
+/// Joins a relative path with `/` regardless of platform.
+pub fn url_path(path: &std::path::Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clone_urls() {
+        let github = RepoLocation::parse("https://github.com/anthropics/skills.git", None).unwrap();
+        assert_eq!(github.forge, Forge::GitHub);
+        assert_eq!(github.repo_url(), "https://github.com/anthropics/skills");
+
+        let gitlab = RepoLocation::parse("git@gitlab.com:group/sub/skills.git", None).unwrap();
+        assert_eq!(gitlab.forge, Forge::GitLab);
+        assert_eq!(gitlab.path, "group/sub/skills");
+
+        let gitea = RepoLocation::parse("ssh://git@git.example.org:2222/team/skills", Some(Forge::Gitea)).unwrap();
+        assert_eq!(gitea.repo_url(), "https://git.example.org/team/skills");
+
+        assert!(RepoLocation::parse("/local/path/skills", None).is_none());
+        assert!(RepoLocation::parse("file:///srv/skills", None).is_none());
+        assert!(RepoLocation::parse("./repos/a:b/skills", None).is_none());
+    }
+
+    #[test]
+    fn test_repo_links() {
+        let links = RepoLinks::new("https://github.com/openai/skills.git", None, "main", Some("abc123"));
+        let dir = std::path::Path::new("skills/.curated/pdf");
+        assert_eq!(links.tree_url(dir), "https://github.com/openai/skills/tree/main/skills/.curated/pdf");
+        assert_eq!(
+            links.permalink(dir).unwrap(),
+            "https://github.com/openai/skills/tree/abc123/skills/.curated/pdf"
+        );
+
+        let no_head = RepoLinks::new("https://github.com/openai/skills.git", None, "main", None);
+        assert!(no_head.permalink(dir).is_none());
+
+        // A file:// remote has no web page to link to
+        let local = RepoLinks::new("file:///srv/skills", None, "main", Some("abc123"));
+        assert_eq!(local.tree_url(dir), "file:///srv/skills/skills/.curated/pdf");
+        assert!(local.permalink(dir).is_none());
+    }
+
+    #[test]
+    fn test_tree_urls_per_forge() {
+        let sha = "0123456789abcdef";
+        let github = RepoLocation::parse("https://github.com/jo-inc/skills", None).unwrap();
+        assert_eq!(
+            github.tree_url(Rev::Branch("master"), "skills/pdf"),
+            "https://github.com/jo-inc/skills/tree/master/skills/pdf"
+        );
+
+        let gitlab = RepoLocation::parse("https://gitlab.com/team/skills.git", None).unwrap();
+        assert_eq!(
+            gitlab.tree_url(Rev::Commit(sha), "skills/pdf"),
+            "https://gitlab.com/team/skills/-/tree/0123456789abcdef/skills/pdf"
+        );
+
+        let gitea = RepoLocation::parse("https://codeberg.org/team/skills.git", None).unwrap();
+        assert_eq!(
+            gitea.tree_url(Rev::Branch("main"), "pdf"),
+            "https://codeberg.org/team/skills/src/branch/main/pdf"
+        );
+        assert_eq!(
+            gitea.tree_url(Rev::Commit(sha), "pdf"),
+            "https://codeberg.org/team/skills/src/commit/0123456789abcdef/pdf"
+        );
+    }
+}
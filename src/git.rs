@@ -0,0 +1,789 @@
+use crate::db::CommitInfo;
+use anyhow::{Context, Result};
+use gix::bstr::{BStr, BString, ByteSlice};
+use gix::config::tree::gitoxide::Committer;
+use gix::progress::tree;
+use gix::refs::transaction::{PreviousValue, RefEdit};
+use gix::remote::fetch::{Shallow, Tags};
+use gix::remote::Direction;
+use gix::ObjectId;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long a clone or fetch may run before it is cancelled, unless the registry's
+/// config sets `timeout_secs`.
+pub const DEFAULT_GIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Commits of history fetched below HEAD to find when each skill last changed.
+pub const HISTORY_DEPTH: NonZeroU32 = NonZeroU32::new(200).unwrap();
+
+/// How often a running clone or fetch logs its progress.
+const PROGRESS_EVERY: Duration = Duration::from_secs(5);
+
+/// How often the watchdog checks for a timeout or cancellation.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+const REFLOG_MESSAGE: &str = "safe-skill-search: sync";
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// A clone or fetch stopped by its timeout or by [`cancel`].
+#[derive(Debug)]
+struct Interrupted(String);
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Cancels running clones and fetches, and makes new ones fail immediately; called
+/// on Ctrl-C so a sync can finish with what it has.
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// What to check out of a remote.
+enum Target<'a> {
+    /// The remote's default branch, followed by a local branch of the same name
+    DefaultBranch,
+    /// A branch, tag or commit, checked out as a detached HEAD
+    Ref(&'a str),
+}
+
+/// Shallow-clones `url` into `repo_dir`, or fetches the remote's default branch into
+/// an existing clone and resets the checkout to it.
+pub fn clone_or_pull(url: &str, repo_dir: &Path, timeout: Duration) -> Result<()> {
+    refresh(url, repo_dir, timeout, Target::DefaultBranch)
+}
+
+/// Checks out `git_ref` (a branch, tag or commit) of `url` in `repo_dir` as a detached
+/// HEAD, creating the repository first if needed.
+pub fn checkout_ref(url: &str, repo_dir: &Path, git_ref: &str, timeout: Duration) -> Result<()> {
+    refresh(url, repo_dir, timeout, Target::Ref(git_ref))
+}
+
+/// Brings `repo_dir` to `target` without leaving it half-updated. An existing clone
+/// fetches, then swaps in a worktree checked out next to it, so force-pushes and local
+/// changes don't matter; a missing or broken clone is replaced by a fresh one cloned
+/// next to it. If that fails too, the previous checkout is left as it was.
+fn refresh(url: &str, repo_dir: &Path, timeout: Duration, target: Target) -> Result<()> {
+    if repo_dir.join(".git").exists() {
+        tracing::info!("Fetching {} into {:?}", url, repo_dir);
+        match update(url, repo_dir, timeout, &target) {
+            Ok(()) => return Ok(()),
+            // Cloning afresh would only hit the same timeout, or ignore the cancel
+            Err(e) if e.is::<Interrupted>() => return Err(e),
+            Err(e) => tracing::warn!("Updating {:?} failed, cloning afresh: {:#}", repo_dir, e),
+        }
+    }
+    tracing::info!("Cloning {} into {:?}", url, repo_dir);
+    fresh_clone(url, repo_dir, timeout, &target)
+}
+
+fn update(url: &str, repo_dir: &Path, timeout: Duration, target: &Target) -> Result<()> {
+    let repo = with_committer(gix::open(repo_dir)?)?;
+    let (commit, branch) = watched(url, timeout, |progress, interrupt| {
+        resolve(&repo, url, target, progress, interrupt)
+    })?;
+    // Nothing changed upstream and nothing was touched locally: leave the checkout be
+    if is_current(&repo, commit, branch.as_ref().map(|b| b.as_bstr()))? {
+        return Ok(());
+    }
+
+    let staging = sibling(repo_dir, "checkout");
+    remove_dir_if_exists(&staging)?;
+    let mut index = match checkout(&repo, commit, &staging) {
+        Ok(index) => index,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    // Move the repository into the new worktree, then swap the worktrees
+    std::fs::rename(repo_dir.join(".git"), staging.join(".git"))?;
+    if let Err(e) = replace_dir(&staging, repo_dir) {
+        let _ = std::fs::rename(staging.join(".git"), repo_dir.join(".git"));
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    index.write(Default::default())?;
+    set_head(&repo, commit, branch.as_ref().map(|b| b.as_bstr()))
+}
+
+fn fresh_clone(url: &str, repo_dir: &Path, timeout: Duration, target: &Target) -> Result<()> {
+    let staging = sibling(repo_dir, "cloning");
+    remove_dir_if_exists(&staging)?;
+    let cloned = (|| {
+        std::fs::create_dir_all(&staging)?;
+        let repo = with_committer(gix::init(&staging)?)?;
+        let (commit, branch) = watched(url, timeout, |progress, interrupt| {
+            resolve(&repo, url, target, progress, interrupt)
+        })?;
+        let mut index = checkout(&repo, commit, &staging)?;
+        index.write(Default::default())?;
+        set_head(&repo, commit, branch.as_ref().map(|b| b.as_bstr()))
+    })();
+    if let Err(e) = cloned {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    replace_dir(&staging, repo_dir)
+}
+
+/// Fetches `target` into `repo`, returning the commit to check out and, when following
+/// the default branch, the branch's full ref name.
+fn resolve(
+    repo: &gix::Repository,
+    url: &str,
+    target: &Target,
+    progress: &mut tree::Item,
+    interrupt: &AtomicBool,
+) -> Result<(ObjectId, Option<BString>)> {
+    let git_ref = match target {
+        Target::DefaultBranch => {
+            let outcome = fetch(
+                repo,
+                url,
+                &["+HEAD:refs/remotes/origin/HEAD"],
+                initial_depth(repo),
+                progress,
+                interrupt,
+            )?;
+            let (id, branch) = outcome
+                .ref_map
+                .remote_refs
+                .iter()
+                .find_map(|r| match r {
+                    gix::protocol::handshake::Ref::Symbolic {
+                        full_ref_name,
+                        target,
+                        object,
+                        ..
+                    } if full_ref_name == "HEAD" => Some((object.to_owned(), Some(target.clone()))),
+                    gix::protocol::handshake::Ref::Direct { full_ref_name, object } if full_ref_name == "HEAD" => {
+                        Some((object.to_owned(), None))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow::anyhow!("{} has no HEAD to check out", url))?;
+            return Ok((peel_to_commit(repo, id)?, branch));
+        }
+        Target::Ref(git_ref) => *git_ref,
+    };
+
+    let fetched = fetch(repo, url, &[git_ref], initial_depth(repo), progress, interrupt)
+        .map_err(|e| tracing::debug!("Fetching {} directly failed: {:#}", git_ref, e))
+        .ok()
+        .and_then(|outcome| {
+            let mapping = outcome.ref_map.mappings.first()?;
+            mapping.remote.as_id().map(|id| id.to_owned())
+        });
+    let id = match fetched {
+        Some(id) => id,
+        None => {
+            // Abbreviated commit ids can't be fetched directly, so fetch everything and resolve locally
+            tracing::debug!("Fetching full history of {} to find {}", url, git_ref);
+            let depth = if repo.is_shallow()? { Shallow::undo() } else { Shallow::NoChange };
+            fetch(
+                repo,
+                url,
+                &["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+                depth,
+                progress,
+                interrupt,
+            )?;
+            [git_ref.to_string(), format!("origin/{}", git_ref)]
+                .iter()
+                .find_map(|spec| repo.rev_parse_single(spec.as_str()).ok())
+                .ok_or_else(|| anyhow::anyhow!("ref '{}' not found in {}", git_ref, url))?
+                .detach()
+        }
+    };
+    Ok((peel_to_commit(repo, id)?, None))
+}
+
+/// Deepens a shallow clone to `depth` commits below HEAD, so commit history can tell
+/// when each skill last changed without fetching the repository's whole past. Does
+/// nothing once that much history is present.
+pub fn deepen(url: &str, repo_dir: &Path, depth: NonZeroU32, timeout: Duration) -> Result<()> {
+    let repo = gix::open(repo_dir)?;
+    if !repo.is_shallow()? {
+        return Ok(());
+    }
+    let head = repo.head_id()?.detach();
+    let present = repo.rev_walk([head]).all()?.take(depth.get() as usize).count();
+    if present >= depth.get() as usize {
+        return Ok(());
+    }
+
+    tracing::info!("Fetching {} commits of history for {:?}", depth, repo_dir);
+    let head = head.to_string();
+    watched(url, timeout, |progress, interrupt| {
+        fetch(&repo, url, &[head.as_str()], Shallow::DepthAtRemote(depth), progress, interrupt).map(|_| ())
+    })
+}
+
+/// Finds the last commit that touched each of `dirs`, walking history from HEAD and
+/// comparing only the trees on the way to a wanted directory, and stopping as soon as
+/// every directory has been seen. Directories not changed above a shallow boundary are
+/// left out rather than credited to the boundary commit.
+pub fn last_commits(repo_dir: &Path, dirs: &[PathBuf]) -> Result<HashMap<PathBuf, CommitInfo>> {
+    let repo = gix::open(repo_dir)?;
+    let wanted: HashSet<PathBuf> = dirs
+        .iter()
+        .filter_map(|d| d.strip_prefix(repo_dir).ok())
+        .map(Path::to_path_buf)
+        .collect();
+    let ancestors: HashSet<PathBuf> = wanted
+        .iter()
+        .flat_map(|d| d.ancestors().skip(1).map(Path::to_path_buf))
+        .collect();
+
+    let boundary: HashSet<ObjectId> = repo
+        .shallow_commits()?
+        .map(|commits| commits.iter().copied().collect())
+        .unwrap_or_default();
+    let mut found: HashMap<PathBuf, CommitInfo> = HashMap::new();
+    let walk = repo
+        .rev_walk([repo.head_id()?.detach()])
+        .sorting(gix::revision::walk::Sorting::ByCommitTime(Default::default()))
+        .all()?;
+    for info in walk {
+        if found.len() == wanted.len() {
+            break;
+        }
+        let info = info?;
+        // Without its parents there's no telling what a boundary commit changed
+        if boundary.contains(&info.id) {
+            continue;
+        }
+        let commit = info.object()?;
+        let tree = Some(commit.tree_id()?.detach());
+
+        // A directory counts as changed by a commit if it differs from every parent
+        let mut parent_trees = Vec::new();
+        for parent in info.parent_ids() {
+            if let Ok(parent) = parent.object().map(|o| o.into_commit()) {
+                parent_trees.push(Some(parent.tree_id()?.detach()));
+            }
+        }
+        if parent_trees.is_empty() {
+            parent_trees.push(None);
+        }
+        let mut changed: Option<HashSet<PathBuf>> = None;
+        for parent_tree in parent_trees {
+            let mut dirs = HashSet::new();
+            changed_dirs(&repo, tree, parent_tree, Path::new(""), &wanted, &ancestors, &mut dirs)?;
+            changed = Some(match changed {
+                Some(previous) => previous.intersection(&dirs).cloned().collect(),
+                None => dirs,
+            });
+        }
+
+        let changed = changed.unwrap_or_default();
+        if changed.iter().all(|d| found.contains_key(d)) {
+            continue;
+        }
+        let author = commit.author()?.name.trim().to_str_lossy().into_owned();
+        let entry = CommitInfo {
+            sha: info.id.to_string(),
+            time: commit.time()?.seconds,
+            author,
+        };
+        for dir in changed {
+            found.entry(dir).or_insert_with(|| entry.clone());
+        }
+    }
+
+    Ok(found.into_iter().map(|(dir, commit)| (repo_dir.join(dir), commit)).collect())
+}
+
+/// Collects the wanted directories whose tree differs between `new` and `old`,
+/// descending only into `ancestors` of wanted directories.
+fn changed_dirs(
+    repo: &gix::Repository,
+    new: Option<ObjectId>,
+    old: Option<ObjectId>,
+    dir: &Path,
+    wanted: &HashSet<PathBuf>,
+    ancestors: &HashSet<PathBuf>,
+    changed: &mut HashSet<PathBuf>,
+) -> Result<()> {
+    if new == old {
+        return Ok(());
+    }
+    if wanted.contains(dir) {
+        changed.insert(dir.to_path_buf());
+    }
+    if !ancestors.contains(dir) {
+        return Ok(());
+    }
+    let new_entries = subtrees(repo, new)?;
+    let old_entries = subtrees(repo, old)?;
+    for name in new_entries.keys().chain(old_entries.keys().filter(|n| !new_entries.contains_key(*n))) {
+        let child = dir.join(name);
+        if wanted.contains(&child) || ancestors.contains(&child) {
+            let (new, old) = (new_entries.get(name).copied(), old_entries.get(name).copied());
+            changed_dirs(repo, new, old, &child, wanted, ancestors, changed)?;
+        }
+    }
+    Ok(())
+}
+
+/// The directories directly inside a tree, by name.
+fn subtrees(repo: &gix::Repository, tree: Option<ObjectId>) -> Result<HashMap<String, ObjectId>> {
+    let Some(tree) = tree else {
+        return Ok(HashMap::new());
+    };
+    let tree = repo.find_tree(tree)?;
+    let mut entries = HashMap::new();
+    for entry in tree.iter() {
+        let entry = entry?;
+        if entry.mode().is_tree() {
+            entries.insert(entry.filename().to_str_lossy().into_owned(), entry.object_id());
+        }
+    }
+    Ok(entries)
+}
+
+/// Looks up commit info for the skill directories of a fresh clone, within the last
+/// `HISTORY_DEPTH` commits, falling back to none (and sync time as `updated_at`) for
+/// skills untouched in that window or if the history can't be read.
+pub fn skill_commits(url: &str, repo_dir: &Path, dirs: &[PathBuf], timeout: Duration) -> HashMap<PathBuf, CommitInfo> {
+    if let Err(e) = deepen(url, repo_dir, HISTORY_DEPTH, timeout) {
+        tracing::warn!("Using shallow history for {:?}: {:#}", repo_dir, e);
+    }
+    last_commits(repo_dir, dirs).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read commit history of {:?}: {:#}", repo_dir, e);
+        HashMap::new()
+    })
+}
+
+/// Returns the commit checked out in a clone.
+pub fn head_commit(repo_dir: &Path) -> Result<String> {
+    Ok(gix::open(repo_dir)?.head_id()?.to_string())
+}
+
+/// Returns the branch checked out in a clone (the remote's default branch after a
+/// clone), or `HEAD` for a detached checkout.
+pub fn current_branch(repo_dir: &Path) -> Result<String> {
+    Ok(match gix::open(repo_dir)?.head_name()? {
+        Some(name) => name.shorten().to_string(),
+        None => "HEAD".to_string(),
+    })
+}
+
+/// Lets ref updates be logged when no git identity is configured.
+fn with_committer(mut repo: gix::Repository) -> Result<gix::Repository> {
+    let mut config = repo.config_snapshot_mut();
+    config.set_value(&Committer::NAME_FALLBACK, "safe-skill-search")?;
+    config.set_value(&Committer::EMAIL_FALLBACK, "safe-skill-search@localhost")?;
+    config.commit()?;
+    Ok(repo)
+}
+
+/// A hidden working directory next to `dir`, e.g. `.anthropic.cloning`.
+fn sibling(dir: &Path, purpose: &str) -> PathBuf {
+    dir.with_file_name(format!(
+        ".{}.{}",
+        dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+        purpose
+    ))
+}
+
+fn remove_dir_if_exists(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Moves `new` to `dest`, replacing what was there only once the move can't fail halfway.
+fn replace_dir(new: &Path, dest: &Path) -> Result<()> {
+    let old = sibling(dest, "old");
+    remove_dir_if_exists(&old)?;
+    if dest.exists() {
+        std::fs::rename(dest, &old)?;
+    }
+    if let Err(e) = std::fs::rename(new, dest) {
+        let _ = std::fs::rename(&old, dest);
+        return Err(e.into());
+    }
+    remove_dir_if_exists(&old)
+}
+
+fn has_head(repo: &gix::Repository) -> bool {
+    repo.head_id().is_ok()
+}
+
+/// New clones start with only the latest commit; existing ones keep their history.
+fn initial_depth(repo: &gix::Repository) -> Shallow {
+    if has_head(repo) {
+        Shallow::NoChange
+    } else {
+        Shallow::DepthAtRemote(NonZeroU32::MIN)
+    }
+}
+
+fn fetch(
+    repo: &gix::Repository,
+    url: &str,
+    refspecs: &[&str],
+    shallow: Shallow,
+    progress: &mut tree::Item,
+    interrupt: &AtomicBool,
+) -> Result<gix::remote::fetch::Outcome> {
+    let remote = repo
+        .remote_at(url)?
+        .with_refspecs(refspecs.iter().copied(), Direction::Fetch)?
+        .with_fetch_tags(Tags::None);
+    let outcome = remote
+        .connect(Direction::Fetch)?
+        .prepare_fetch(&mut *progress, Default::default())?
+        .with_shallow(shallow)
+        .receive(&mut *progress, interrupt)?;
+    Ok(outcome)
+}
+
+fn peel_to_commit(repo: &gix::Repository, id: ObjectId) -> Result<ObjectId> {
+    Ok(repo
+        .find_object(id)?
+        .peel_to_commit()
+        .with_context(|| format!("{} is not a commit", id))?
+        .id)
+}
+
+/// Writes the tree of `commit` into the empty directory `dest`, returning the index
+/// to save once `dest` is the repository's worktree.
+fn checkout(repo: &gix::Repository, commit: ObjectId, dest: &Path) -> Result<gix::index::File> {
+    let tree = repo.find_commit(commit)?.tree_id()?.detach();
+    let mut index = repo.index_from_tree(&tree)?;
+    std::fs::create_dir_all(dest)?;
+
+    let mut options = repo.checkout_options(gix::worktree::stack::state::attributes::Source::IdMapping)?;
+    options.destination_is_initially_empty = true;
+    let objects = repo.objects.clone().into_arc()?;
+    let progress = gix::progress::Discard;
+    let interrupt = AtomicBool::new(false);
+    gix::worktree::state::checkout(&mut index, dest, objects, &progress, &progress, &interrupt, options)?;
+    Ok(index)
+}
+
+/// Whether HEAD already points at `commit` (through `branch`, if given) and the
+/// worktree matches it exactly, with no edited, deleted or untracked files.
+fn is_current(repo: &gix::Repository, commit: ObjectId, branch: Option<&BStr>) -> Result<bool> {
+    let head = repo.head()?;
+    let same_ref = match (branch, head.referent_name()) {
+        (Some(branch), Some(name)) => name.as_bstr() == branch,
+        (None, None) => true,
+        _ => false,
+    };
+    if !same_ref || head.id().map(|id| id.detach()) != Some(commit) {
+        return Ok(false);
+    }
+    let mut changes = repo
+        .status(gix::progress::Discard)?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_index_worktree_iter(Vec::new())?;
+    Ok(changes.next().transpose()?.is_none())
+}
+
+/// Points HEAD at `commit`: through `branch` (a full ref name) when the remote's HEAD
+/// is a branch, otherwise detached.
+fn set_head(repo: &gix::Repository, commit: ObjectId, branch: Option<&BStr>) -> Result<()> {
+    let head: gix::refs::FullName = "HEAD".try_into()?;
+    match branch {
+        Some(branch) => {
+            let branch: gix::refs::FullName = branch.try_into()?;
+            repo.edit_references([
+                RefEdit::update(branch.clone(), commit, PreviousValue::Any, REFLOG_MESSAGE),
+                RefEdit::update(head, branch, PreviousValue::Any, REFLOG_MESSAGE),
+            ])?;
+        }
+        None => {
+            repo.edit_reference(RefEdit::update(head, commit, PreviousValue::Any, REFLOG_MESSAGE))?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs a network operation on `url` while a watchdog thread logs its progress and
+/// interrupts it on timeout or [`cancel`].
+fn watched<T>(url: &str, timeout: Duration, op: impl FnOnce(&mut tree::Item, &AtomicBool) -> Result<T>) -> Result<T> {
+    if CANCELLED.load(Ordering::SeqCst) {
+        return Err(Interrupted(format!("fetching {} cancelled", url)).into());
+    }
+    let root = tree::Root::new();
+    let mut progress = root.add_child(url);
+    let interrupt = AtomicBool::new(false);
+    let done = AtomicBool::new(false);
+    let deadline = Instant::now() + timeout;
+
+    let result = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut last_report = Instant::now();
+            while !done.load(Ordering::SeqCst) {
+                std::thread::sleep(WATCH_INTERVAL);
+                if CANCELLED.load(Ordering::SeqCst) || Instant::now() >= deadline {
+                    interrupt.store(true, Ordering::SeqCst);
+                }
+                if last_report.elapsed() >= PROGRESS_EVERY {
+                    log_progress(url, &root);
+                    last_report = Instant::now();
+                }
+            }
+        });
+        let result = op(&mut progress, &interrupt);
+        done.store(true, Ordering::SeqCst);
+        result
+    });
+
+    match result {
+        Err(_) if interrupt.load(Ordering::SeqCst) && CANCELLED.load(Ordering::SeqCst) => {
+            Err(Interrupted(format!("fetching {} cancelled", url)).into())
+        }
+        Err(_) if interrupt.load(Ordering::SeqCst) => {
+            Err(Interrupted(format!("fetching {} timed out after {}s", url, timeout.as_secs())).into())
+        }
+        result => result,
+    }
+}
+
+fn log_progress(url: &str, root: &tree::Root) {
+    let mut tasks = Vec::new();
+    root.sorted_snapshot(&mut tasks);
+    let status: Vec<String> = tasks
+        .iter()
+        .filter_map(|(_, task)| {
+            let value = task.progress.as_ref()?;
+            let step = value.step.load(Ordering::Relaxed);
+            (step > 0).then(|| match value.done_at {
+                Some(total) => format!("{} {}/{}", task.name, step, total),
+                None => format!("{} {}", task.name, step),
+            })
+        })
+        .collect();
+    if !status.is_empty() {
+        tracing::info!("{}: {}", url, status.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::find_skill_dirs;
+    use std::process::Command;
+
+    fn git(repo: &Path, args: &[&str], date: &str) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Tester", "-c", "user.email=t@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .current_dir(repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_last_commits_per_skill_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        git(repo, &["init", "-q"], "@1700000000 +0000");
+        for skill in ["skills/old", "skills/new", "skills/author/nested"] {
+            std::fs::create_dir_all(repo.join(skill)).unwrap();
+            std::fs::write(repo.join(skill).join("SKILL.md"), "# Skill").unwrap();
+        }
+        git(repo, &["add", "."], "@1700000000 +0000");
+        git(repo, &["commit", "-q", "-m", "initial"], "@1700000000 +0000");
+        std::fs::write(repo.join("skills/new/SKILL.md"), "# Changed").unwrap();
+        std::fs::write(repo.join("README.md"), "unrelated").unwrap();
+        git(repo, &["commit", "-q", "-am", "update"], "@1710000000 +0000");
+
+        let skills_dir = repo.join("skills");
+        let dirs = find_skill_dirs(&skills_dir, 2);
+        let url = format!("file://{}", repo.display());
+        let commits = skill_commits(&url, repo, &dirs, DEFAULT_GIT_TIMEOUT);
+
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits[&repo.join("skills/old")].time, 1700000000);
+        assert_eq!(commits[&repo.join("skills/author/nested")].time, 1700000000);
+        let new = &commits[&repo.join("skills/new")];
+        assert_eq!(new.time, 1710000000);
+        assert_eq!(new.author, "Tester");
+        assert_eq!(new.sha.len(), 40);
+    }
+
+    #[test]
+    fn test_clone_pull_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir_all(upstream.join("skills/pdf")).unwrap();
+        git(&upstream, &["init", "-q", "-b", "trunk"], "@1700000000 +0000");
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# v1").unwrap();
+        git(&upstream, &["add", "."], "@1700000000 +0000");
+        git(&upstream, &["commit", "-q", "-m", "v1"], "@1700000000 +0000");
+
+        let url = format!("file://{}", upstream.display());
+        let clone = dir.path().join("clone");
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(current_branch(&clone).unwrap(), "trunk");
+        assert_eq!(head_commit(&clone).unwrap(), head_commit(&upstream).unwrap());
+        assert!(gix::open(&clone).unwrap().is_shallow().unwrap());
+
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# v2").unwrap();
+        std::fs::create_dir_all(upstream.join("skills/docx")).unwrap();
+        std::fs::write(upstream.join("skills/docx/SKILL.md"), "# docx").unwrap();
+        git(&upstream, &["add", "."], "@1710000000 +0000");
+        git(&upstream, &["commit", "-q", "-m", "v2"], "@1710000000 +0000");
+        std::fs::write(clone.join("stray.txt"), "left over").unwrap();
+
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), head_commit(&upstream).unwrap());
+        assert_eq!(std::fs::read_to_string(clone.join("skills/pdf/SKILL.md")).unwrap(), "# v2");
+        assert!(clone.join("skills/docx/SKILL.md").exists());
+        assert!(!clone.join("stray.txt").exists());
+
+        let dirs = find_skill_dirs(&clone.join("skills"), 2);
+        let commits = skill_commits(&url, &clone, &dirs, DEFAULT_GIT_TIMEOUT);
+        assert_eq!(commits[&clone.join("skills/pdf")].time, 1710000000);
+        assert_eq!(commits[&clone.join("skills/docx")].time, 1710000000);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        for skill in ["skills/old", "skills/new"] {
+            std::fs::create_dir_all(upstream.join(skill)).unwrap();
+            std::fs::write(upstream.join(skill).join("SKILL.md"), "# Skill").unwrap();
+        }
+        git(&upstream, &["init", "-q"], "@1700000000 +0000");
+        git(&upstream, &["add", "."], "@1700000000 +0000");
+        git(&upstream, &["commit", "-q", "-m", "initial"], "@1700000000 +0000");
+        for (i, file) in ["README.md", "skills/new/SKILL.md", "NOTES.md"].iter().enumerate() {
+            std::fs::write(upstream.join(file), "changed").unwrap();
+            git(&upstream, &["add", "."], &format!("@{} +0000", 1710000000 + i));
+            git(&upstream, &["commit", "-q", "-m", file], &format!("@{} +0000", 1710000000 + i));
+        }
+
+        let url = format!("file://{}", upstream.display());
+        let clone = dir.path().join("clone");
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        let depth = NonZeroU32::new(3).unwrap();
+        deepen(&url, &clone, depth, DEFAULT_GIT_TIMEOUT).unwrap();
+        let repo = gix::open(&clone).unwrap();
+        assert!(repo.is_shallow().unwrap());
+        assert_eq!(repo.rev_walk([repo.head_id().unwrap().detach()]).all().unwrap().count(), 3);
+
+        // The commit adding both skills is below the fetched history, so "old" has none
+        let dirs = find_skill_dirs(&clone.join("skills"), 2);
+        let commits = last_commits(&clone, &dirs).unwrap();
+        assert_eq!(commits[&clone.join("skills/new")].time, 1710000001);
+        assert!(!commits.contains_key(&clone.join("skills/old")));
+    }
+
+    #[test]
+    fn test_refresh_recovers_and_keeps_previous_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir_all(upstream.join("skills/pdf")).unwrap();
+        git(&upstream, &["init", "-q"], "@1700000000 +0000");
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# v1").unwrap();
+        git(&upstream, &["add", "."], "@1700000000 +0000");
+        git(&upstream, &["commit", "-q", "-m", "v1"], "@1700000000 +0000");
+
+        let url = format!("file://{}", upstream.display());
+        let clone = dir.path().join("clone");
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+
+        // An unchanged upstream leaves a clean checkout untouched
+        let skill = clone.join("skills/pdf/SKILL.md");
+        let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        std::fs::File::options().write(true).open(&skill).unwrap().set_modified(old).unwrap();
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(std::fs::metadata(&skill).unwrap().modified().unwrap(), old);
+
+        // ...but stray files are still cleaned up
+        std::fs::write(clone.join("skills/pdf/stray.md"), "stray").unwrap();
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert!(!clone.join("skills/pdf/stray.md").exists());
+        assert_eq!(std::fs::read_to_string(&skill).unwrap(), "# v1");
+
+        // Rewritten upstream history and local edits are reset to the remote
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# rewritten").unwrap();
+        git(&upstream, &["commit", "-q", "--amend", "-am", "rewritten"], "@1710000000 +0000");
+        std::fs::write(clone.join("skills/pdf/SKILL.md"), "# local edit").unwrap();
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), head_commit(&upstream).unwrap());
+        assert_eq!(std::fs::read_to_string(clone.join("skills/pdf/SKILL.md")).unwrap(), "# rewritten");
+
+        // A broken clone is replaced by a fresh one
+        std::fs::write(clone.join(".git/HEAD"), "garbage").unwrap();
+        clone_or_pull(&url, &clone, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), head_commit(&upstream).unwrap());
+
+        // A failed refresh leaves the previous checkout and no staging directories
+        let missing = format!("file://{}", dir.path().join("missing").display());
+        assert!(clone_or_pull(&missing, &clone, DEFAULT_GIT_TIMEOUT).is_err());
+        assert_eq!(std::fs::read_to_string(clone.join("skills/pdf/SKILL.md")).unwrap(), "# rewritten");
+        assert_eq!(head_commit(&clone).unwrap(), head_commit(&upstream).unwrap());
+        let mut entries: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, ["clone", "upstream"]);
+    }
+
+    #[test]
+    fn test_checkout_pinned_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir_all(upstream.join("skills/pdf")).unwrap();
+        git(&upstream, &["init", "-q"], "@1700000000 +0000");
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# v1").unwrap();
+        git(&upstream, &["add", "."], "@1700000000 +0000");
+        git(&upstream, &["commit", "-q", "-m", "v1"], "@1700000000 +0000");
+        git(&upstream, &["tag", "-a", "v1", "-m", "v1"], "@1700000000 +0000");
+        let v1 = head_commit(&upstream).unwrap();
+        std::fs::write(upstream.join("skills/pdf/SKILL.md"), "# v2").unwrap();
+        git(&upstream, &["commit", "-q", "-am", "v2"], "@1710000000 +0000");
+        let v2 = head_commit(&upstream).unwrap();
+
+        let url = format!("file://{}", upstream.display());
+        let clone = dir.path().join("clone");
+        checkout_ref(&url, &clone, "v1", DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), v1);
+        assert_eq!(current_branch(&clone).unwrap(), "HEAD");
+        assert_eq!(std::fs::read_to_string(clone.join("skills/pdf/SKILL.md")).unwrap(), "# v1");
+
+        checkout_ref(&url, &clone, &v2, DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), v2);
+
+        checkout_ref(&url, &clone, &v1[..10], DEFAULT_GIT_TIMEOUT).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), v1);
+
+        assert!(checkout_ref(&url, &clone, "no-such-ref", DEFAULT_GIT_TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn test_fetch_timeout() {
+        let error = watched("file:///srv/skills", Duration::ZERO, |_, interrupt| -> Result<()> {
+            while !interrupt.load(Ordering::SeqCst) {
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+            anyhow::bail!("interrupted")
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+    }
+}
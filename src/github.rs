@@ -1,51 +1,139 @@
-use crate::db::{Database, Skill};
+use crate::db::{CommitInfo, Skill};
+use crate::forge::{Forge, RepoLinks};
+use crate::git;
+use crate::http::HttpClient;
+use crate::metrics::MetricKind;
+use crate::report::{short_sha, SkillError};
+use crate::source::{SkillSource, SourceContext, SourceSink};
+use crate::sync::MetricUpdate;
 use anyhow::Result;
-use serde::Deserialize;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where a registry's skills come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryKind {
+    /// A git repository, cloned into `repos/`
+    #[default]
+    Git,
+    /// A local directory, scanned in place
+    Path,
+    /// A `.tar.gz` or `.zip` file, extracted into `repos/`
+    Archive,
+    /// An external command that prints skills as JSON Lines; see `plugin.rs`
+    Plugin,
+}
+
+/// An API a git registry's popularity metrics are fetched from, alongside the clone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsApi {
+    /// Star and download counts from the clawdhub API
+    Clawdhub,
+}
 
 #[derive(Debug, Clone)]
 pub struct Registry {
-    pub name: &'static str,
-    pub repo_url: &'static str,
-    pub skills_path: &'static str,
+    pub name: String,
+    pub kind: RegistryKind,
+    /// Clone URL of a git registry.
+    pub repo_url: String,
+    /// Directory or archive (a local path or `file://` URL) of a path or archive registry.
+    pub path: Option<String>,
+    /// Directory holding the skills, relative to the repository, directory or archive root.
+    pub skills_path: String,
     pub trusted: bool,
+    /// Web URL layout of the remote; detected from the host when `None`.
+    pub forge: Option<Forge>,
+    /// Branch, tag or commit to check out instead of the remote's default branch.
+    pub git_ref: Option<String>,
+    /// Executable of a plugin registry, and its arguments.
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    /// How long a plugin may run, or a git clone or fetch may take; `None` means
+    /// `plugin::DEFAULT_PLUGIN_TIMEOUT` or `git::DEFAULT_GIT_TIMEOUT`.
+    pub timeout_secs: Option<u64>,
+    /// Seconds after a successful sync before the registry's data counts as stale;
+    /// `None` means the config's default `max_age`.
+    pub max_age: Option<i64>,
+    /// Where a git registry's metrics come from; `None` means it has none.
+    pub metrics: Option<MetricsApi>,
 }
 
-pub const REGISTRIES: &[Registry] = &[
-    Registry {
-        name: "clawdhub",
-        repo_url: "https://github.com/openclaw/skills.git",
-        skills_path: "skills",
-        trusted: false, // Community skills, need individual verification
-    },
-    Registry {
-        name: "anthropic",
-        repo_url: "https://github.com/anthropics/skills.git",
-        skills_path: "skills",
-        trusted: true, // Official Anthropic skills
-    },
-    Registry {
-        name: "openai",
-        repo_url: "https://github.com/openai/skills.git",
-        skills_path: "skills/.curated",
-        trusted: true, // Official OpenAI curated skills
-    },
-    Registry {
-        name: "openai-experimental",
-        repo_url: "https://github.com/openai/skills.git",
-        skills_path: "skills/.experimental",
-        trusted: false, // Experimental skills, not yet curated
-    },
-    Registry {
-        name: "jo",
-        repo_url: "https://github.com/jo-inc/skills.git",
-        skills_path: "skills",
-        trusted: true, // Official jo skills
-    },
-];
+impl Registry {
+    fn builtin(name: &str, repo_url: &str, skills_path: &str, trusted: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: RegistryKind::Git,
+            repo_url: repo_url.to_string(),
+            path: None,
+            skills_path: skills_path.to_string(),
+            trusted,
+            forge: None,
+            git_ref: None,
+            command: None,
+            args: Vec::new(),
+            timeout_secs: None,
+            max_age: None,
+            metrics: None,
+        }
+    }
+}
+
+impl Registry {
+    /// The directory or archive of a path or archive registry.
+    pub fn local_source(&self) -> Result<&str> {
+        self.path
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("{} registry '{}' has no path", self.kind.as_str(), self.name))
+    }
+
+    /// Whether the registry syncs without the network: a path or archive registry, or a
+    /// git registry cloned from a `file://` remote. Plugins may fetch anything.
+    pub fn is_local(&self) -> bool {
+        match self.kind {
+            RegistryKind::Path | RegistryKind::Archive => true,
+            RegistryKind::Git => self.repo_url.starts_with("file://"),
+            RegistryKind::Plugin => false,
+        }
+    }
+}
+
+impl RegistryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Git => "git",
+            Self::Path => "path",
+            Self::Archive => "archive",
+            Self::Plugin => "plugin",
+        }
+    }
+}
+
+/// The registries synced when `registries.yaml` doesn't change them.
+pub fn builtin_registries() -> Vec<Registry> {
+    vec![
+        // Community skills, need individual verification
+        Registry {
+            metrics: Some(MetricsApi::Clawdhub),
+            ..Registry::builtin("clawdhub", "https://github.com/openclaw/skills.git", "skills", false)
+        },
+        // Official Anthropic skills
+        Registry::builtin("anthropic", "https://github.com/anthropics/skills.git", "skills", true),
+        // Official OpenAI curated skills
+        Registry::builtin("openai", "https://github.com/openai/skills.git", "skills/.curated", true),
+        // Experimental skills, not yet curated
+        Registry::builtin("openai-experimental", "https://github.com/openai/skills.git", "skills/.experimental", false),
+        // Official jo skills
+        Registry::builtin("jo", "https://github.com/jo-inc/skills.git", "skills", true),
+    ]
+}
+
+const CLAWDHUB_API: &str = "https://clawhub.com/api/v1";
 
 #[derive(Debug, Deserialize)]
 struct ClawdhubSkill {
@@ -56,6 +144,8 @@ struct ClawdhubSkill {
 #[derive(Debug, Deserialize)]
 struct ClawdhubStats {
     stars: i64,
+    #[serde(default)]
+    downloads: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,54 +155,50 @@ struct ClawdhubResponse {
     next_cursor: Option<String>,
 }
 
-pub async fn sync_all_registries(db: &mut Database, repos_dir: &Path) -> Result<()> {
-    std::fs::create_dir_all(repos_dir)?;
-
-    for registry in REGISTRIES {
-        tracing::info!("Syncing registry: {}", registry.name);
-        if let Err(e) = sync_registry(db, repos_dir, registry).await {
-            tracing::warn!("Failed to sync {}: {}", registry.name, e);
-        }
-    }
-
-    // Fetch star counts from clawdhub API
-    tracing::info!("Fetching star counts from clawdhub API...");
-    if let Err(e) = fetch_clawdhub_stars(db).await {
-        tracing::warn!("Failed to fetch clawdhub stars: {}", e);
-    }
-
-    Ok(())
-}
-
-async fn fetch_clawdhub_stars(db: &mut Database) -> Result<()> {
-    let client = reqwest::Client::builder()
-        .user_agent("skill-search/0.1")
-        .build()?;
-
-    let mut stars_map: HashMap<String, i64> = HashMap::new();
+/// Fetches clawdhub stars and downloads page by page.
+///
+/// Each page is sent to the writer as soon as it arrives, so a page that still fails
+/// after retries ends the fetch without losing the pages before it.
+pub async fn fetch_clawdhub_stars(http: &HttpClient, sink: &SourceSink) -> Result<()> {
     let mut cursor: Option<String> = None;
     let mut page = 0;
+    let mut fetched = 0;
 
     loop {
         let url = match &cursor {
-            Some(c) => format!("https://clawhub.com/api/v1/skills?limit=100&cursor={}", c),
-            None => "https://clawhub.com/api/v1/skills?limit=100".to_string(),
+            Some(c) => format!("{}/skills?limit=100&cursor={}", CLAWDHUB_API, c),
+            None => format!("{}/skills?limit=100", CLAWDHUB_API),
         };
 
-        let resp = client.get(&url).send().await?;
-        if !resp.status().is_success() {
-            anyhow::bail!("Clawdhub API error: {}", resp.status());
-        }
+        let data: ClawdhubResponse = match http.get_json(&url).await {
+            Ok(data) => data,
+            Err(e) if fetched > 0 => {
+                anyhow::bail!("Clawdhub API failed after {} skills ({} pages kept): {}", fetched, page, e)
+            }
+            Err(e) => return Err(e),
+        };
 
-        let data: ClawdhubResponse = resp.json().await?;
-        
+        fetched += data.items.len();
+        let mut metrics = Vec::new();
         for skill in data.items {
-            stars_map.insert(skill.slug, skill.stats.stars);
+            if let Some(downloads) = skill.stats.downloads {
+                metrics.push(MetricUpdate {
+                    slug: skill.slug.clone(),
+                    kind: MetricKind::Downloads,
+                    value: downloads,
+                });
+            }
+            metrics.push(MetricUpdate {
+                slug: skill.slug,
+                kind: MetricKind::Stars,
+                value: skill.stats.stars,
+            });
         }
+        sink.metrics(CLAWDHUB_API, metrics).await?;
 
         page += 1;
         if page % 10 == 0 {
-            tracing::debug!("Fetched {} skills from clawdhub API", stars_map.len());
+            tracing::debug!("Fetched {} skills from clawdhub API", fetched);
         }
 
         match data.next_cursor {
@@ -121,74 +207,164 @@ async fn fetch_clawdhub_stars(db: &mut Database) -> Result<()> {
         }
     }
 
-    tracing::info!("Fetched stars for {} clawdhub skills", stars_map.len());
+    tracing::info!("Fetched stars for {} clawdhub skills", fetched);
+    Ok(())
+}
 
-    // Update stars in database
-    for (slug, stars) in stars_map {
-        db.update_stars("clawdhub", &slug, stars)?;
-    }
+/// A registry backed by a git repository, cloned into `repos/`.
+pub struct GitSource {
+    registry: Registry,
+}
 
-    Ok(())
+impl GitSource {
+    pub fn new(registry: Registry) -> Self {
+        Self { registry }
+    }
 }
 
-async fn sync_registry(db: &mut Database, repos_dir: &Path, registry: &Registry) -> Result<()> {
-    let repo_dir = repos_dir.join(registry.name);
-
-    // Clone or pull
-    if repo_dir.join(".git").exists() {
-        tracing::info!("Pulling updates for {}", registry.name);
-        let status = Command::new("git")
-            .args(["pull", "--ff-only", "-q"])
-            .current_dir(&repo_dir)
-            .status()?;
-        if !status.success() {
-            tracing::warn!("git pull failed for {}, trying fresh clone", registry.name);
-            std::fs::remove_dir_all(&repo_dir)?;
-            clone_repo(registry.repo_url, &repo_dir)?;
-        }
-    } else {
-        clone_repo(registry.repo_url, &repo_dir)?;
+#[async_trait]
+impl SkillSource for GitSource {
+    fn name(&self) -> &str {
+        &self.registry.name
     }
 
-    // Scan for skills
-    let skills_dir = repo_dir.join(registry.skills_path);
-    if !skills_dir.exists() {
-        anyhow::bail!("Skills directory not found: {:?}", skills_dir);
+    /// Clones or updates the repository, checked out at `git_ref` if one is set and
+    /// otherwise following the remote's default branch, and scans it for skills.
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        let _permit = ctx.limiter.acquire().await?;
+        let registry = self.registry.clone();
+        let repo_dir = ctx.repos_dir.join(&registry.name);
+
+        let (skills, failed, head) = tokio::task::spawn_blocking(move || -> Result<(Vec<Skill>, Vec<SkillError>, Option<String>)> {
+            let timeout = registry.timeout_secs.map_or(git::DEFAULT_GIT_TIMEOUT, Duration::from_secs);
+            let refreshed = match &registry.git_ref {
+                Some(git_ref) => git::checkout_ref(&registry.repo_url, &repo_dir, git_ref, timeout),
+                None => git::clone_or_pull(&registry.repo_url, &repo_dir, timeout),
+            };
+            // The registry's skills stay as last synced, from the checkout that's still there
+            if let Err(e) = refreshed {
+                return Err(match git::head_commit(&repo_dir) {
+                    Ok(previous) => e.context(format!("keeping the snapshot at {}", short_sha(&previous))),
+                    Err(_) => e,
+                });
+            }
+
+            // Scan for skills
+            let skills_dir = repo_dir.join(&registry.skills_path);
+            if !skills_dir.exists() {
+                anyhow::bail!("Skills directory not found: {:?}", skills_dir);
+            }
+
+            let head = git::head_commit(&repo_dir).ok();
+            // A pinned checkout is detached, so browse URLs use the ref itself
+            let branch = match &registry.git_ref {
+                Some(git_ref) => git_ref.clone(),
+                None => git::current_branch(&repo_dir)?,
+            };
+            let links = RepoLinks::new(&registry.repo_url, registry.forge, &branch, head.as_deref());
+            let commits = git::skill_commits(&registry.repo_url, &repo_dir, &find_skill_dirs(&skills_dir, 2), timeout);
+            let (skills, failed) = scan_skills_dir(&registry, &skills_dir, &repo_dir, &links, &commits)?;
+            Ok((skills, failed, head))
+        })
+        .await??;
+
+        tracing::info!("Found {} skills in {}", skills.len(), self.registry.name);
+        sink.skills(skills, failed).await?;
+        sink.complete(head).await
     }
 
-    scan_skills_dir(db, registry, &skills_dir, &repo_dir)?;
-    
-    // Count skills
-    let mut count = 0;
-    for entry in std::fs::read_dir(&skills_dir)? {
-        let entry = entry?;
-        if entry.file_type()?.is_dir() {
-            count += 1;
-        }
+    /// Reads the skill from the checkout left by the last sync.
+    async fn fetch_skill(&self, ctx: &SourceContext, slug: &str) -> Result<String> {
+        let skills_dir = ctx.repos_dir.join(&self.registry.name).join(&self.registry.skills_path);
+        read_skill_md(&skills_dir, slug)
     }
+}
 
-    tracing::info!("Synced {} skills from {}", count, registry.name);
+/// The clawdhub git registry, with star and download counts from the clawdhub API.
+pub struct ClawdhubSource(GitSource);
 
-    // Update sync state
-    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
-    db.set_last_sync(registry.name, now, None)?;
+impl ClawdhubSource {
+    pub fn new(git: GitSource) -> Self {
+        Self(git)
+    }
+}
 
-    Ok(())
+#[async_trait]
+impl SkillSource for ClawdhubSource {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        self.0.list_skills(ctx, sink).await
+    }
+
+    async fn fetch_skill(&self, ctx: &SourceContext, slug: &str) -> Result<String> {
+        self.0.fetch_skill(ctx, slug).await
+    }
+
+    async fn fetch_metrics(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        tracing::info!("Fetching star counts from clawdhub API...");
+        fetch_clawdhub_stars(&ctx.http, sink).await
+    }
 }
 
-fn clone_repo(url: &str, dest: &Path) -> Result<()> {
-    tracing::info!("Cloning {} to {:?}", url, dest);
-    let status = Command::new("git")
-        .args(["clone", "--depth", "1", "-q", url])
-        .arg(dest)
-        .status()?;
-    if !status.success() {
-        anyhow::bail!("git clone failed");
+/// Finds every directory below `root` that contains a SKILL.md, up to `max_depth` levels deep.
+pub fn find_skill_dirs(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut stack = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        if dir.join("SKILL.md").is_file() {
+            found.push(dir.clone());
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hidden = entry.file_name().to_string_lossy() == ".git";
+                if path.is_dir() && !hidden {
+                    stack.push((path, depth + 1));
+                }
+            }
+        }
     }
-    Ok(())
+    found.sort();
+    found
+}
+
+/// Reads the SKILL.md of the skill `slug` below `skills_dir`, found the way
+/// [`scan_skills_dir`] finds it: by directory name, up to two levels deep.
+pub fn read_skill_md(skills_dir: &Path, slug: &str) -> Result<String> {
+    let dir = find_skill_dirs(skills_dir, 2)
+        .into_iter()
+        .find(|d| d.file_name().is_some_and(|n| n == slug))
+        .ok_or_else(|| anyhow::anyhow!("skill '{}' not found in {:?}", slug, skills_dir))?;
+    Ok(std::fs::read_to_string(dir.join("SKILL.md"))?)
 }
 
-fn scan_skills_dir(db: &mut Database, registry: &Registry, dir: &Path, repo_root: &Path) -> Result<()> {
+/// Reads every skill under `dir`, returning the parsed skills and the ones that failed.
+pub fn scan_skills_dir(
+    registry: &Registry,
+    dir: &Path,
+    repo_root: &Path,
+    links: &RepoLinks,
+    commits: &HashMap<PathBuf, CommitInfo>,
+) -> Result<(Vec<Skill>, Vec<SkillError>)> {
+    let mut skills = Vec::new();
+    let mut failed = Vec::new();
+    let mut process = |path: &Path, skill_md_path: &Path| match process_skill(registry, path, skill_md_path, repo_root, links, commits.get(path)) {
+        Ok(skill) => skills.push(skill),
+        Err(e) => {
+            tracing::debug!("Skipping {:?}: {}", path, e);
+            failed.push(SkillError {
+                slug: path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string(),
+                error: e.to_string(),
+            });
+        }
+    };
+
     for entry in std::fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
@@ -199,9 +375,7 @@ fn scan_skills_dir(db: &mut Database, registry: &Registry, dir: &Path, repo_root
 
         let skill_md_path = path.join("SKILL.md");
         if skill_md_path.exists() {
-            if let Err(e) = process_skill(db, registry, &path, &skill_md_path, repo_root) {
-                tracing::debug!("Skipping {:?}: {}", path, e);
-            }
+            process(&path, &skill_md_path);
         } else {
             // Check subdirectories (for nested structure like clawdhub's author/skill)
             if let Ok(entries) = std::fs::read_dir(&path) {
@@ -210,19 +384,24 @@ fn scan_skills_dir(db: &mut Database, registry: &Registry, dir: &Path, repo_root
                     if sub_path.is_dir() {
                         let sub_skill_md = sub_path.join("SKILL.md");
                         if sub_skill_md.exists() {
-                            if let Err(e) = process_skill(db, registry, &sub_path, &sub_skill_md, repo_root) {
-                                tracing::debug!("Skipping {:?}: {}", sub_path, e);
-                            }
+                            process(&sub_path, &sub_skill_md);
                         }
                     }
                 }
             }
         }
     }
-    Ok(())
+    Ok((skills, failed))
 }
 
-fn process_skill(db: &mut Database, registry: &Registry, skill_dir: &Path, skill_md_path: &Path, repo_root: &Path) -> Result<()> {
+fn process_skill(
+    registry: &Registry,
+    skill_dir: &Path,
+    skill_md_path: &Path,
+    repo_root: &Path,
+    links: &RepoLinks,
+    commit: Option<&CommitInfo>,
+) -> Result<Skill> {
     let skill_md = std::fs::read_to_string(skill_md_path)?;
     let (name, description, version) = parse_skill_frontmatter(&skill_md);
 
@@ -233,15 +412,9 @@ fn process_skill(db: &mut Database, registry: &Registry, skill_dir: &Path, skill
         .unwrap_or("unknown")
         .to_string();
 
-    // Build GitHub URL from relative path
     let rel_path = skill_dir.strip_prefix(repo_root).unwrap_or(skill_dir);
-    let github_url = match registry.name {
-        "clawdhub" => format!("https://github.com/openclaw/skills/tree/main/{}", rel_path.display()),
-        "anthropic" => format!("https://github.com/anthropics/skills/tree/main/{}", rel_path.display()),
-        "openai" | "openai-experimental" => format!("https://github.com/openai/skills/tree/main/{}", rel_path.display()),
-        "jo" => format!("https://github.com/jo-inc/skills/tree/master/{}", rel_path.display()),
-        _ => format!("https://github.com/unknown/{}", rel_path.display()),
-    };
+    let github_url = links.tree_url(rel_path);
+    let permalink = links.permalink(rel_path);
 
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
 
@@ -249,18 +422,19 @@ fn process_skill(db: &mut Database, registry: &Registry, skill_dir: &Path, skill
         id: 0,
         slug,
         name,
-        registry: registry.name.to_string(),
+        registry: registry.name.clone(),
         description,
         skill_md,
         github_url,
         version,
         stars: 0, // Will be updated from clawdhub API
         trusted: registry.trusted,
-        updated_at: now,
+        updated_at: commit.map_or(now, |c| c.time),
+        commit: commit.cloned(),
+        permalink,
     };
 
-    db.upsert_skill(&skill)?;
-    Ok(())
+    Ok(skill)
 }
 
 pub fn parse_skill_frontmatter(content: &str) -> (String, String, Option<String>) {
@@ -268,9 +442,9 @@ pub fn parse_skill_frontmatter(content: &str) -> (String, String, Option<String>
     let mut description = String::new();
     let mut version = None;
 
-    if content.starts_with("---") {
-        if let Some(end_idx) = content[3..].find("---") {
-            let frontmatter = &content[3..3 + end_idx];
+    if let Some(rest) = content.strip_prefix("---") {
+        if let Some(end_idx) = rest.find("---") {
+            let frontmatter = &rest[..end_idx];
 
             for line in frontmatter.lines() {
                 let line = line.trim();
@@ -379,26 +553,28 @@ This skill does cool things.
 
     #[test]
     fn test_registries_configuration() {
-        assert_eq!(REGISTRIES.len(), 5);
-        
-        let clawdhub = &REGISTRIES[0];
+        let registries = builtin_registries();
+        assert_eq!(registries.len(), 5);
+
+        let clawdhub = &registries[0];
         assert_eq!(clawdhub.name, "clawdhub");
         assert!(!clawdhub.trusted);
-        
-        let anthropic = &REGISTRIES[1];
+
+        let anthropic = &registries[1];
         assert_eq!(anthropic.name, "anthropic");
         assert!(anthropic.trusted);
-        
-        let openai = &REGISTRIES[2];
+
+        let openai = &registries[2];
         assert_eq!(openai.name, "openai");
         assert!(openai.trusted);
-        
-        let openai_exp = &REGISTRIES[3];
+
+        let openai_exp = &registries[3];
         assert_eq!(openai_exp.name, "openai-experimental");
         assert!(!openai_exp.trusted);
 
-        let jo = &REGISTRIES[4];
+        let jo = &registries[4];
         assert_eq!(jo.name, "jo");
         assert!(jo.trusted);
+        assert!(registries.iter().all(|r| r.git_ref.is_none()));
     }
 }
@@ -0,0 +1,269 @@
+use crate::sync::{WriteOp, WriteSender};
+use anyhow::Result;
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const USER_AGENT: &str = "skill-search/0.1";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A response body saved with its `ETag`, replayed when the server answers 304.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+pub type HttpCache = HashMap<String, CachedResponse>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter for the given (zero-based) retry.
+    fn backoff(&self, retry: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << retry.min(16));
+        let jitter_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64 % (self.base_delay.as_millis() as u64 + 1))
+            .unwrap_or(0);
+        (exp + Duration::from_millis(jitter_ms)).min(self.max_delay)
+    }
+}
+
+/// HTTP client shared by the registry APIs: timeouts, retries with backoff,
+/// `Retry-After` on 429/503, and conditional requests from stored `ETag`s.
+///
+/// New `ETag`s are sent to the sync writer, so the cache is persisted with the rest
+/// of the sync results.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    policy: RetryPolicy,
+    cache: Arc<Mutex<HttpCache>>,
+    tx: Option<WriteSender>,
+}
+
+impl HttpClient {
+    pub fn new(cache: HttpCache, tx: Option<WriteSender>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            policy: RetryPolicy::default(),
+            cache: Arc::new(Mutex::new(cache)),
+            tx,
+        })
+    }
+
+    /// Replaces the retry policy, so tests don't wait out real backoff delays.
+    #[cfg(test)]
+    fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// GETs `url` and parses the JSON body, retrying transient failures.
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let body = self.get_text(url).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// GETs `url`, returning the body (or the cached body on 304 Not Modified).
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        let cached = self.cache.lock().unwrap().get(url).cloned();
+        let mut retry = 0;
+        loop {
+            let mut request = self.client.get(url);
+            if let Some(c) = &cached {
+                request = request.header(IF_NONE_MATCH, &c.etag);
+            }
+
+            let delay = match request.send().await {
+                Ok(resp) if resp.status() == StatusCode::NOT_MODIFIED && cached.is_some() => {
+                    tracing::debug!("Not modified: {}", url);
+                    return Ok(cached.map(|c| c.body).unwrap_or_default());
+                }
+                Ok(resp) if resp.status().is_success() => {
+                    let etag = header_str(resp.headers(), ETAG);
+                    let body = resp.text().await?;
+                    if let Some(etag) = etag {
+                        self.store(url, CachedResponse { etag, body: body.clone() }).await;
+                    }
+                    return Ok(body);
+                }
+                Ok(resp) if is_retryable(resp.status()) => {
+                    let retry_after = header_str(resp.headers(), RETRY_AFTER)
+                        .and_then(|v| parse_retry_after(&v, SystemTime::now()));
+                    if retry + 1 >= self.policy.max_attempts {
+                        anyhow::bail!("{} returned {} after {} attempts", url, resp.status(), retry + 1);
+                    }
+                    tracing::debug!("{} returned {}, retrying", url, resp.status());
+                    retry_after
+                        .map(|d| d.min(self.policy.max_delay))
+                        .unwrap_or_else(|| self.policy.backoff(retry))
+                }
+                Ok(resp) => anyhow::bail!("{} returned {}", url, resp.status()),
+                Err(e) if retry + 1 < self.policy.max_attempts && (e.is_timeout() || e.is_connect()) => {
+                    tracing::debug!("Request to {} failed, retrying: {}", url, e);
+                    self.policy.backoff(retry)
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    async fn store(&self, url: &str, response: CachedResponse) {
+        self.cache.lock().unwrap().insert(url.to_string(), response.clone());
+        if let Some(tx) = &self.tx {
+            let op = WriteOp::HttpCache {
+                url: url.to_string(),
+                response,
+            };
+            if tx.send(op).await.is_err() {
+                tracing::debug!("Sync writer closed, not caching {}", url);
+            }
+        }
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+/// Rate limits, gateway errors and overloaded servers are worth retrying.
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT || status.is_server_error()
+}
+
+/// Parses a `Retry-After` header: either delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves one canned response per connection and records each request's headers.
+    async fn serve(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                seen.lock().unwrap().push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+        (url, requests)
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:37 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default();
+        assert!(policy.backoff(0) < policy.backoff(3));
+        assert_eq!(policy.backoff(20), policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limited_requests() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\n{\"n\": 1}",
+        ])
+        .await;
+        let client = HttpClient::new(HttpCache::new(), None).unwrap().with_policy(fast_policy());
+
+        let value: serde_json::Value = client.get_json(&url).await.unwrap();
+        assert_eq!(value["n"], 1);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_on_client_errors() {
+        let (url, requests) = serve(vec!["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"]).await;
+        let client = HttpClient::new(HttpCache::new(), None).unwrap().with_policy(fast_policy());
+
+        assert!(client.get_text(&url).await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_request_replays_cached_body() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\n\r\nfresh",
+            "HTTP/1.1 304 Not Modified\r\n\r\n",
+        ])
+        .await;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let client = HttpClient::new(HttpCache::new(), Some(tx)).unwrap().with_policy(fast_policy());
+
+        assert_eq!(client.get_text(&url).await.unwrap(), "fresh");
+        assert!(matches!(rx.recv().await, Some(WriteOp::HttpCache { .. })));
+
+        assert_eq!(client.get_text(&url).await.unwrap(), "fresh");
+        let requests = requests.lock().unwrap();
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+    }
+}
@@ -1,10 +1,11 @@
 use crate::db::Database;
+use crate::semantic::SemanticModel;
 use anyhow::Result;
 use std::path::Path;
 use tantivy::collector::TopDocs;
 use tantivy::query::{BooleanQuery, Occur, QueryParser, TermQuery};
 use tantivy::schema::{IndexRecordOption, Schema, STORED, STRING, TEXT, Field, Value};
-use tantivy::{Index, IndexWriter, Term, TantivyDocument};
+use tantivy::{Index, IndexWriter, ReloadPolicy, Term, TantivyDocument};
 
 pub struct SearchIndex {
     index: Index,
@@ -67,11 +68,15 @@ impl SearchIndex {
 
         index_writer.commit()?;
         tracing::info!("Index rebuilt");
+
+        SemanticModel::rebuild(db)?;
         Ok(())
     }
 
     pub fn search(&self, query_str: &str, limit: usize, registry: Option<&str>) -> Result<Vec<SearchResult>> {
-        let reader = self.index.reader()?;
+        // Pinned to the last commit: a sync rebuilding the index meanwhile doesn't affect
+        // this search, and its old segments stay readable until the reader is dropped
+        let reader = self.index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
         let searcher = reader.searcher();
 
         let query_parser = QueryParser::for_index(
@@ -162,6 +167,8 @@ mod tests {
             stars: 0,
             trusted: registry == "anthropic",
             updated_at: 1234567890,
+            commit: None,
+            permalink: None,
         }
     }
 
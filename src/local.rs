@@ -0,0 +1,124 @@
+use crate::archive;
+use crate::db::Skill;
+use crate::forge::RepoLinks;
+use crate::github::{read_skill_md, scan_skills_dir, Registry, RegistryKind};
+use crate::report::SkillError;
+use crate::source::{SkillSource, SourceContext, SourceSink};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// A registry read from disk: a directory scanned in place, or a `.tar.gz`/`.zip`
+/// archive extracted into `repos/` first.
+pub struct LocalSource {
+    registry: Registry,
+}
+
+impl LocalSource {
+    pub fn new(registry: Registry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl SkillSource for LocalSource {
+    fn name(&self) -> &str {
+        &self.registry.name
+    }
+
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        let _permit = ctx.limiter.acquire().await?;
+        let registry = self.registry.clone();
+        let extract_dir = ctx.repos_dir.join(&registry.name);
+
+        let (skills, failed) = tokio::task::spawn_blocking(move || -> Result<(Vec<Skill>, Vec<SkillError>)> {
+            let location = archive::local_path(registry.local_source()?)?;
+            let root = if registry.kind == RegistryKind::Archive {
+                tracing::info!("Extracting {:?} to {:?}", location, extract_dir);
+                archive::extract(&location, &extract_dir)?;
+                archive::content_root(&extract_dir, &registry.skills_path)?
+            } else {
+                location
+            };
+
+            let skills_dir = root.join(&registry.skills_path);
+            if !skills_dir.exists() {
+                anyhow::bail!("Skills directory not found: {:?}", skills_dir);
+            }
+            scan_skills_dir(&registry, &skills_dir, &root, &RepoLinks::local(&root), &HashMap::new())
+        })
+        .await??;
+
+        tracing::info!("Found {} skills in {}", skills.len(), self.registry.name);
+        sink.skills(skills, failed).await?;
+        sink.complete(None).await
+    }
+
+    /// Reads the skill in place, or from the archive as the last sync extracted it.
+    async fn fetch_skill(&self, ctx: &SourceContext, slug: &str) -> Result<String> {
+        let root = match self.registry.kind {
+            RegistryKind::Archive => {
+                archive::content_root(&ctx.repos_dir.join(&self.registry.name), &self.registry.skills_path)?
+            }
+            _ => archive::local_path(self.registry.local_source()?)?,
+        };
+        read_skill_md(&root.join(&self.registry.skills_path), slug)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HttpCache, HttpClient};
+    use crate::sync::WriteOp;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_list_path_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared");
+        std::fs::create_dir_all(shared.join("pdf")).unwrap();
+        std::fs::write(shared.join("pdf/SKILL.md"), "---\nname: pdf\ndescription: PDFs\n---\n").unwrap();
+        std::fs::create_dir_all(shared.join("team/notes")).unwrap();
+        std::fs::write(shared.join("team/notes/SKILL.md"), "# Notes").unwrap();
+
+        let source = LocalSource::new(Registry {
+            name: "shared".to_string(),
+            kind: RegistryKind::Path,
+            repo_url: String::new(),
+            path: Some(shared.display().to_string()),
+            skills_path: String::new(),
+            trusted: true,
+            forge: None,
+            git_ref: None,
+            command: None,
+            args: Vec::new(),
+            timeout_secs: None,
+            max_age: None,
+            metrics: None,
+        });
+        let ctx = SourceContext {
+            repos_dir: dir.path().join("repos"),
+            http: HttpClient::new(HttpCache::new(), None).unwrap(),
+            limiter: Arc::new(tokio::sync::Semaphore::new(1)),
+            cursor: None,
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        source.list_skills(&ctx, &SourceSink::new("shared", tx)).await.unwrap();
+
+        let Some(WriteOp::Skills { mut skills, .. }) = rx.recv().await else {
+            panic!("expected skills");
+        };
+        skills.sort_by(|a, b| a.slug.cmp(&b.slug));
+        assert_eq!(skills.len(), 2);
+        assert_eq!(skills[1].slug, "pdf");
+        assert!(skills[1].trusted);
+        assert_eq!(skills[1].github_url, format!("file://{}/pdf", shared.display()));
+        assert!(skills[1].permalink.is_none());
+        assert!(!dir.path().join("repos/shared").exists());
+        assert!(matches!(rx.recv().await, Some(WriteOp::Synced { commit: None, .. })));
+
+        assert_eq!(source.fetch_skill(&ctx, "notes").await.unwrap(), "# Notes");
+        assert!(source.fetch_skill(&ctx, "missing").await.is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const LOCK_FILE: &str = "sync.lock";
+
+/// An advisory lock on the data directory, held for the whole of a sync so that two
+/// syncs never mutate `repos/`, the database and the index at the same time.
+///
+/// The lock is released when this is dropped, or by the OS if the process dies, so a
+/// crashed sync never leaves a stale lock behind. Searches don't take it.
+pub struct SyncLock {
+    _file: File,
+}
+
+impl SyncLock {
+    /// Takes the lock, failing straight away if another sync holds it.
+    pub fn acquire(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(LOCK_FILE);
+        let mut file = open(&path)?;
+        match file.try_lock() {
+            Ok(()) => Self::locked(file),
+            Err(TryLockError::WouldBlock) => {
+                let mut holder = String::new();
+                let _ = file.read_to_string(&mut holder);
+                let holder = match holder.trim() {
+                    "" => String::new(),
+                    pid => format!(" (pid {})", pid),
+                };
+                anyhow::bail!("another sync is already running{}; lock held on {}", holder, path.display())
+            }
+            Err(TryLockError::Error(e)) => Err(e).with_context(|| format!("locking {}", path.display())),
+        }
+    }
+
+    /// Takes the lock, waiting for a sync that holds it to finish first.
+    pub fn wait(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(LOCK_FILE);
+        let file = open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                tracing::info!("Waiting for another sync to finish...");
+                file.lock().with_context(|| format!("locking {}", path.display()))?;
+            }
+            Err(TryLockError::Error(e)) => return Err(e).with_context(|| format!("locking {}", path.display())),
+        }
+        Self::locked(file)
+    }
+
+    /// Records who holds the lock, for the error another sync reports.
+    fn locked(mut file: File) -> Result<Self> {
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+}
+
+fn open(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_second_sync_is_refused_until_first_finishes() {
+        let dir = tempdir().unwrap();
+
+        let held = SyncLock::acquire(dir.path()).unwrap();
+        let err = SyncLock::acquire(dir.path()).err().unwrap().to_string();
+        assert!(err.contains("another sync is already running"), "{}", err);
+        assert!(err.contains(&format!("pid {}", std::process::id())), "{}", err);
+
+        drop(held);
+        let _held = SyncLock::acquire(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_wait_blocks_until_lock_is_released() {
+        let dir = tempdir().unwrap();
+        let held = SyncLock::acquire(dir.path()).unwrap();
+
+        let path = dir.path().to_path_buf();
+        let waiter = std::thread::spawn(move || SyncLock::wait(&path).map(|_| ()));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+
+        drop(held);
+        waiter.join().unwrap().unwrap();
+    }
+}
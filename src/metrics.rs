@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A popularity signal reported by a registry. Values of different kinds are never
+/// compared directly; see `popularity_scores`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricKind {
+    Stars,
+    Installs,
+    Downloads,
+    Forks,
+}
+
+impl MetricKind {
+    pub const ALL: [MetricKind; 4] = [Self::Stars, Self::Installs, Self::Downloads, Self::Forks];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stars => "stars",
+            Self::Installs => "installs",
+            Self::Downloads => "downloads",
+            Self::Forks => "forks",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Stars => "Stars",
+            Self::Installs => "Installs",
+            Self::Downloads => "Downloads",
+            Self::Forks => "Forks",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == s)
+    }
+}
+
+/// One measured value of a metric, with where and when it was fetched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metric {
+    pub skill_id: i64,
+    pub registry: String,
+    pub kind: MetricKind,
+    pub value: i64,
+    pub source: String,
+    pub fetched_at: i64,
+}
+
+/// Scores every skill's popularity in `[0, 1]` relative to its own registry.
+///
+/// Each metric is log-scaled and divided by the registry's maximum for that metric,
+/// so 10k skills.sh installs and 10k clawdhub stars don't compete on raw counts.
+/// A skill's popularity is its best normalized metric.
+pub fn popularity_scores(metrics: &[Metric]) -> HashMap<i64, f64> {
+    let mut max_by_registry: HashMap<(&str, MetricKind), f64> = HashMap::new();
+    for m in metrics {
+        let v = (m.value.max(0) as f64).ln_1p();
+        let max = max_by_registry.entry((m.registry.as_str(), m.kind)).or_insert(0.0);
+        *max = max.max(v);
+    }
+
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+    for m in metrics {
+        let max = max_by_registry[&(m.registry.as_str(), m.kind)];
+        let normalized = if max > 0.0 { (m.value.max(0) as f64).ln_1p() / max } else { 0.0 };
+        let score = scores.entry(m.skill_id).or_insert(0.0);
+        *score = score.max(normalized);
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(skill_id: i64, registry: &str, kind: MetricKind, value: i64) -> Metric {
+        Metric {
+            skill_id,
+            registry: registry.to_string(),
+            kind,
+            value,
+            source: "test".to_string(),
+            fetched_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_metric_kind_parse() {
+        assert_eq!(MetricKind::parse("installs"), Some(MetricKind::Installs));
+        assert_eq!(MetricKind::parse("likes"), None);
+    }
+
+    #[test]
+    fn test_popularity_is_normalized_per_registry() {
+        let metrics = vec![
+            metric(1, "clawdhub", MetricKind::Stars, 50),
+            metric(2, "clawdhub", MetricKind::Stars, 5),
+            metric(3, "skillssh", MetricKind::Installs, 90_000),
+            metric(4, "skillssh", MetricKind::Installs, 10),
+        ];
+        let scores = popularity_scores(&metrics);
+
+        // The most starred clawdhub skill ranks level with the most installed skills.sh one
+        assert!((scores[&1] - 1.0).abs() < 1e-9);
+        assert!((scores[&3] - 1.0).abs() < 1e-9);
+        assert!(scores[&2] < scores[&1]);
+        assert!(scores[&4] < scores[&2]);
+    }
+
+    #[test]
+    fn test_popularity_uses_best_metric() {
+        let metrics = vec![
+            metric(1, "clawdhub", MetricKind::Stars, 1),
+            metric(1, "clawdhub", MetricKind::Downloads, 1000),
+            metric(2, "clawdhub", MetricKind::Stars, 100),
+        ];
+        let scores = popularity_scores(&metrics);
+        assert!((scores[&1] - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::db::Skill;
+use crate::github::{parse_skill_frontmatter, Registry};
+use crate::metrics::MetricKind;
+use crate::report::SkillError;
+use crate::source::{SkillSource, SourceContext, SourceSink};
+use crate::sync::MetricUpdate;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+/// How long a plugin may run before it is killed, unless its config says otherwise.
+pub const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Lines of a failed plugin's stderr included in the sync report.
+const STDERR_TAIL: usize = 5;
+
+/// One line of plugin output.
+///
+/// ```json
+/// {"slug": "pdf", "name": "PDF", "description": "...", "skill_md": "---\nname: pdf\n...",
+///  "url": "https://intranet/skills/pdf", "metrics": {"stars": 12, "installs": 340}}
+/// ```
+///
+/// Only `slug` and `url` are required; `name` and `description` default to the
+/// SKILL.md frontmatter. Trust always comes from the registry config, never the plugin.
+#[derive(Debug, Deserialize)]
+struct PluginSkill {
+    slug: String,
+    url: String,
+    name: Option<String>,
+    registry: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    skill_md: String,
+    version: Option<String>,
+    #[serde(default)]
+    metrics: HashMap<String, i64>,
+}
+
+/// A registry whose skills come from an external command that prints them as JSON
+/// Lines on stdout, for sources that need custom auth or scraping.
+///
+/// A plugin that exits with an error, times out or can't be started fails only its own
+/// registry: skills it printed before failing are kept, but nothing is removed.
+pub struct PluginSource {
+    registry: Registry,
+}
+
+impl PluginSource {
+    pub fn new(registry: Registry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl SkillSource for PluginSource {
+    fn name(&self) -> &str {
+        &self.registry.name
+    }
+
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        let Some(command) = &self.registry.command else {
+            anyhow::bail!("plugin registry '{}' has no command", self.registry.name);
+        };
+        let _permit = ctx.limiter.acquire().await?;
+        let timeout = self
+            .registry
+            .timeout_secs
+            .map_or(DEFAULT_PLUGIN_TIMEOUT, Duration::from_secs);
+
+        tracing::info!("Running plugin for {}: {}", self.registry.name, command);
+        let mut child = Command::new(command)
+            .args(&self.registry.args)
+            .env("SKILL_SEARCH_REGISTRY", &self.registry.name)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to start plugin '{}'", command))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("plugin has no stdout"))?;
+        let mut stderr = child.stderr.take().ok_or_else(|| anyhow::anyhow!("plugin has no stderr"))?;
+        let stderr_task = tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text).await;
+            text
+        });
+
+        let mut output = PluginOutput::new(&self.registry);
+        let run = async {
+            let mut lines = BufReader::new(stdout).lines();
+            let mut line_no = 0;
+            while let Some(line) = lines.next_line().await? {
+                line_no += 1;
+                output.add_line(line_no, &line);
+            }
+            anyhow::Ok(child.wait().await?)
+        };
+        let status = tokio::time::timeout(timeout, run).await;
+        let stderr_text = match &status {
+            Err(_) => {
+                let _ = child.start_kill();
+                String::new()
+            }
+            Ok(_) => stderr_task.await.unwrap_or_default(),
+        };
+        for line in stderr_text.lines() {
+            tracing::debug!("[{}] {}", self.registry.name, line);
+        }
+
+        let error = match status {
+            Ok(Ok(status)) if status.success() => None,
+            Ok(Ok(status)) => Some(format!("plugin exited with {}{}", status, stderr_tail(&stderr_text))),
+            Ok(Err(e)) => Some(format!("reading plugin output failed: {}", e)),
+            Err(_) => Some(format!("plugin timed out after {}s", timeout.as_secs())),
+        };
+
+        tracing::info!("Found {} skills in {}", output.skills.len(), self.registry.name);
+        sink.skills(output.skills, output.failed).await?;
+        sink.metrics(command, output.metrics).await?;
+        match error {
+            Some(error) => anyhow::bail!(error),
+            None => sink.complete(None).await,
+        }
+    }
+}
+
+/// Skills, metrics and bad lines parsed from a plugin's stdout.
+struct PluginOutput<'a> {
+    registry: &'a Registry,
+    now: i64,
+    skills: Vec<Skill>,
+    failed: Vec<SkillError>,
+    metrics: Vec<MetricUpdate>,
+}
+
+impl<'a> PluginOutput<'a> {
+    fn new(registry: &'a Registry) -> Self {
+        Self {
+            registry,
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+            skills: Vec::new(),
+            failed: Vec::new(),
+            metrics: Vec::new(),
+        }
+    }
+
+    fn add_line(&mut self, line_no: usize, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let fail = |slug: String, error: String| SkillError { slug, error };
+        let parsed: PluginSkill = match serde_json::from_str(line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.failed.push(fail(format!("line {}", line_no), format!("invalid JSON: {}", e)));
+                return;
+            }
+        };
+        if let Some(registry) = parsed.registry.as_deref().filter(|r| *r != self.registry.name) {
+            self.failed.push(fail(
+                parsed.slug,
+                format!("emitted for registry '{}', expected '{}'", registry, self.registry.name),
+            ));
+            return;
+        }
+        if parsed.slug.is_empty() || parsed.slug.contains('/') {
+            self.failed.push(fail(format!("line {}", line_no), format!("invalid slug '{}'", parsed.slug)));
+            return;
+        }
+
+        for (kind, value) in &parsed.metrics {
+            match MetricKind::parse(kind) {
+                Some(kind) => self.metrics.push(MetricUpdate {
+                    slug: parsed.slug.clone(),
+                    kind,
+                    value: *value,
+                }),
+                None => tracing::debug!("Ignoring unknown metric '{}' for {}", kind, parsed.slug),
+            }
+        }
+
+        let (fm_name, fm_description, fm_version) = parse_skill_frontmatter(&parsed.skill_md);
+        let name = parsed
+            .name
+            .filter(|n| !n.is_empty())
+            .or(Some(fm_name).filter(|n| !n.is_empty()))
+            .unwrap_or_else(|| parsed.slug.clone());
+        self.skills.push(Skill {
+            id: 0,
+            slug: parsed.slug,
+            name,
+            registry: self.registry.name.clone(),
+            description: parsed.description.unwrap_or(fm_description),
+            skill_md: parsed.skill_md,
+            github_url: parsed.url,
+            version: parsed.version.or(fm_version),
+            stars: 0,
+            trusted: self.registry.trusted,
+            updated_at: self.now,
+            commit: None,
+            permalink: None,
+        });
+    }
+}
+
+fn stderr_tail(stderr: &str) -> String {
+    let mut tail: VecDeque<&str> = VecDeque::new();
+    for line in stderr.lines().filter(|l| !l.trim().is_empty()) {
+        if tail.len() == STDERR_TAIL {
+            tail.pop_front();
+        }
+        tail.push_back(line.trim());
+    }
+    if tail.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", Vec::from(tail).join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::RegistryKind;
+    use crate::http::{HttpCache, HttpClient};
+    use crate::sync::WriteOp;
+    use std::sync::Arc;
+
+    fn registry(script: &str, timeout_secs: Option<u64>) -> Registry {
+        Registry {
+            name: "internal".to_string(),
+            kind: RegistryKind::Plugin,
+            repo_url: String::new(),
+            path: None,
+            skills_path: String::new(),
+            trusted: true,
+            forge: None,
+            git_ref: None,
+            command: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout_secs,
+            max_age: None,
+            metrics: None,
+        }
+    }
+
+    async fn run(registry: Registry) -> (Result<()>, Vec<WriteOp>) {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = SourceContext {
+            repos_dir: dir.path().to_path_buf(),
+            http: HttpClient::new(HttpCache::new(), None).unwrap(),
+            limiter: Arc::new(tokio::sync::Semaphore::new(1)),
+            cursor: None,
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let result = PluginSource::new(registry)
+            .list_skills(&ctx, &SourceSink::new("internal", tx))
+            .await;
+        let mut ops = Vec::new();
+        while let Ok(op) = rx.try_recv() {
+            ops.push(op);
+        }
+        (result, ops)
+    }
+
+    #[tokio::test]
+    async fn test_plugin_skills_are_ingested() {
+        let script = r#"
+printf '%s\n' '{"slug": "pdf", "url": "https://intranet/pdf", "skill_md": "---\nname: PDF Tools\ndescription: Edit PDFs\n---\n", "metrics": {"installs": 42, "likes": 1}}'
+echo 'not json'
+echo '{"slug": "other", "url": "https://intranet/other", "registry": "clawdhub"}'
+echo 'progress' >&2
+"#;
+        let (result, ops) = run(registry(script, None)).await;
+        result.unwrap();
+
+        let WriteOp::Skills { skills, failed, .. } = &ops[0] else {
+            panic!("expected skills");
+        };
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills[0].name, "PDF Tools");
+        assert_eq!(skills[0].description, "Edit PDFs");
+        assert_eq!(skills[0].registry, "internal");
+        assert!(skills[0].trusted);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].slug, "line 2");
+        assert!(failed[1].error.contains("clawdhub"));
+
+        let WriteOp::Metrics { metrics, .. } = &ops[1] else {
+            panic!("expected metrics");
+        };
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].kind, MetricKind::Installs);
+        assert!(matches!(ops[2], WriteOp::Synced { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_failed_plugin_keeps_partial_output() {
+        let script = r#"
+echo '{"slug": "pdf", "url": "https://intranet/pdf"}'
+echo 'token expired' >&2
+exit 3
+"#;
+        let (result, ops) = run(registry(script, None)).await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("token expired"), "{}", error);
+
+        assert!(matches!(&ops[0], WriteOp::Skills { skills, .. } if skills.len() == 1));
+        // Not marked complete, so skills missing from the partial output are kept
+        assert!(!ops.iter().any(|op| matches!(op, WriteOp::Synced { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_plugin_timeout() {
+        let (result, ops) = run(registry("sleep 5", Some(0))).await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(!ops.iter().any(|op| matches!(op, WriteOp::Synced { .. })));
+    }
+}
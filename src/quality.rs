@@ -73,7 +73,7 @@ mod tests {
     #[test]
     fn test_load_quality_scores() {
         let qs = QualityScores::load();
-        assert!(qs.scores.len() > 0);
+        assert!(!qs.scores.is_empty());
     }
 
     #[test]
@@ -0,0 +1,324 @@
+use crate::db::Database;
+use crate::quality::QualityScores;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Skills listed per category in the text report; `--json` always has them all.
+const MAX_LISTED: usize = 20;
+
+/// A skill that was found but could not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillError {
+    pub slug: String,
+    pub error: String,
+}
+
+/// A quality score or risk level that differs from the previous sync.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelChange {
+    pub slug: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// What one sync run did to a single registry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryReport {
+    pub registry: String,
+    /// Why the registry (or part of it) failed to sync, if it did.
+    pub error: Option<String>,
+    /// The commit a git registry was synced at.
+    #[serde(default)]
+    pub commit: Option<String>,
+    pub new: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: usize,
+    pub removed: Vec<String>,
+    pub failed: Vec<SkillError>,
+    pub quality_changed: Vec<LevelChange>,
+    pub risk_changed: Vec<LevelChange>,
+    pub duration_ms: u64,
+}
+
+impl RegistryReport {
+    /// Records a failure, after any recorded earlier in the run.
+    pub fn add_error(&mut self, error: String) {
+        self.error = Some(match self.error.take() {
+            Some(previous) => format!("{}; {}", previous, error),
+            None => error,
+        });
+    }
+}
+
+/// The outcome of a `sync`, saved to `sync_runs` for `sync log`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    #[serde(default)]
+    pub id: i64,
+    pub started_at: i64,
+    pub duration_ms: u64,
+    pub registries: Vec<RegistryReport>,
+}
+
+impl SyncReport {
+    pub fn registry_mut(&mut self, registry: &str) -> &mut RegistryReport {
+        match self.registries.iter().position(|r| r.registry == registry) {
+            Some(i) => &mut self.registries[i],
+            None => {
+                self.registries.push(RegistryReport {
+                    registry: registry.to_string(),
+                    ..Default::default()
+                });
+                self.registries.sort_by(|a, b| a.registry.cmp(&b.registry));
+                self.registry_mut(registry)
+            }
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.registries.iter().all(|r| r.error.is_none())
+    }
+
+    /// Records quality and risk changes of skills that existed before the sync.
+    pub fn record_changes(&mut self, before: &Snapshot, after: &Snapshot) {
+        let mut ids: Vec<&i64> = after.0.keys().collect();
+        ids.sort();
+        for id in ids {
+            let (Some(old), Some(new)) = (before.0.get(id), after.0.get(id)) else {
+                continue;
+            };
+            if old.quality != new.quality {
+                self.registry_mut(&new.registry).quality_changed.push(LevelChange {
+                    slug: new.slug.clone(),
+                    before: old.quality.to_string(),
+                    after: new.quality.to_string(),
+                });
+            }
+            if old.risk != new.risk {
+                self.registry_mut(&new.registry).risk_changed.push(LevelChange {
+                    slug: new.slug.clone(),
+                    before: old.risk.to_string(),
+                    after: new.risk.to_string(),
+                });
+            }
+        }
+    }
+
+    /// One line per run, as shown by `sync log`.
+    pub fn summary(&self) -> String {
+        let total = |f: fn(&RegistryReport) -> usize| self.registries.iter().map(f).sum::<usize>();
+        let failed: Vec<&str> = self
+            .registries
+            .iter()
+            .filter(|r| r.error.is_some())
+            .map(|r| r.registry.as_str())
+            .collect();
+        let mut line = format!(
+            "{} new, {} updated, {} removed, {} failed skills in {:.1}s",
+            total(|r| r.new.len()),
+            total(|r| r.updated.len()),
+            total(|r| r.removed.len()),
+            total(|r| r.failed.len()),
+            self.duration_ms as f64 / 1000.0
+        );
+        if !failed.is_empty() {
+            line.push_str(&format!(" (failed: {})", failed.join(", ")));
+        }
+        line
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sync: {}", self.summary())?;
+        for r in &self.registries {
+            let at = r
+                .commit
+                .as_deref()
+                .map(|c| format!(" at {}", short_sha(c)))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  {}{} ({:.1}s): {} new, {} updated, {} unchanged, {} removed, {} failed",
+                r.registry,
+                at,
+                r.duration_ms as f64 / 1000.0,
+                r.new.len(),
+                r.updated.len(),
+                r.unchanged,
+                r.removed.len(),
+                r.failed.len()
+            )?;
+            if let Some(error) = &r.error {
+                writeln!(f, "    ERROR: {}", error)?;
+            }
+            write_list(f, "+", r.new.iter().cloned())?;
+            write_list(f, "~", r.updated.iter().cloned())?;
+            write_list(f, "-", r.removed.iter().cloned())?;
+            write_list(f, "!", r.failed.iter().map(|e| format!("{}: {}", e.slug, e.error)))?;
+            write_list(
+                f,
+                "*",
+                r.quality_changed
+                    .iter()
+                    .map(|c| format!("{} quality {} -> {}", c.slug, c.before, c.after))
+                    .chain(
+                        r.risk_changed
+                            .iter()
+                            .map(|c| format!("{} risk {} -> {}", c.slug, c.before, c.after)),
+                    ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM UTC`.
+pub fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Abbreviates a commit SHA for display.
+pub fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(12)]
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, marker: &str, items: impl Iterator<Item = String>) -> fmt::Result {
+    let items: Vec<String> = items.collect();
+    for item in items.iter().take(MAX_LISTED) {
+        writeln!(f, "    {} {}", marker, item)?;
+    }
+    if items.len() > MAX_LISTED {
+        writeln!(f, "    {} ... and {} more", marker, items.len() - MAX_LISTED)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkillState {
+    pub registry: String,
+    pub slug: String,
+    pub quality: i64,
+    pub risk: &'static str,
+}
+
+/// Quality score and risk level of every skill, keyed by skill id, taken before and
+/// after a sync to report what changed.
+#[derive(Debug, Default)]
+pub struct Snapshot(pub HashMap<i64, SkillState>);
+
+impl Snapshot {
+    pub fn capture(db: &Database, quality_scores: &QualityScores) -> Result<Self> {
+        let mut risks: HashMap<i64, &'static str> = HashMap::new();
+        for finding in db.get_all_findings()? {
+            let level = risk_level(&finding.severity);
+            let risk = risks.entry(finding.skill_id).or_insert("none");
+            if severity_rank(level) > severity_rank(risk) {
+                *risk = level;
+            }
+        }
+
+        let states = db
+            .get_all_skills()?
+            .into_iter()
+            .map(|s| {
+                let quality = quality_scores
+                    .get_score(&s.registry, &s.slug)
+                    .or_else(|| quality_scores.get_score(&s.registry, &s.name))
+                    .unwrap_or(0);
+                let risk = risks.get(&s.id).copied().unwrap_or("none");
+                (s.id, SkillState { registry: s.registry, slug: s.slug, quality, risk })
+            })
+            .collect();
+        Ok(Self(states))
+    }
+}
+
+fn risk_level(severity: &str) -> &'static str {
+    match severity {
+        "high" => "high",
+        "medium" => "medium",
+        _ => "low",
+    }
+}
+
+fn severity_rank(level: &str) -> u8 {
+    match level {
+        "high" => 3,
+        "medium" => 2,
+        "low" => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(registry: &str, slug: &str, quality: i64, risk: &'static str) -> SkillState {
+        SkillState {
+            registry: registry.to_string(),
+            slug: slug.to_string(),
+            quality,
+            risk,
+        }
+    }
+
+    #[test]
+    fn test_record_changes() {
+        let before = Snapshot(HashMap::from([
+            (1, state("clawdhub", "pdf-tools", 70, "none")),
+            (2, state("anthropic", "pdf", 90, "none")),
+        ]));
+        let after = Snapshot(HashMap::from([
+            (1, state("clawdhub", "pdf-tools", 85, "high")),
+            (2, state("anthropic", "pdf", 90, "none")),
+            (3, state("clawdhub", "fresh", 50, "medium")),
+        ]));
+
+        let mut report = SyncReport::default();
+        report.record_changes(&before, &after);
+
+        assert_eq!(report.registries.len(), 1);
+        let clawdhub = &report.registries[0];
+        assert_eq!(clawdhub.quality_changed[0].before, "70");
+        assert_eq!(clawdhub.quality_changed[0].after, "85");
+        assert_eq!(clawdhub.risk_changed[0].after, "high");
+        // New skills are reported as new, not as changed
+        assert_eq!(clawdhub.risk_changed.len(), 1);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(1770000000), "2026-02-02 02:40 UTC");
+    }
+
+    #[test]
+    fn test_summary_and_text_report() {
+        let mut report = SyncReport {
+            duration_ms: 1500,
+            ..Default::default()
+        };
+        report.registry_mut("jo").new = vec!["calendar".to_string()];
+        report.registry_mut("anthropic").error = Some("git clone failed".to_string());
+        report.registry_mut("jo").removed = (0..25).map(|i| format!("old-{}", i)).collect();
+        report.registry_mut("jo").commit = Some("0123456789abcdef0123".to_string());
+
+        assert_eq!(report.registries[0].registry, "anthropic");
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.summary(),
+            "1 new, 0 updated, 25 removed, 0 failed skills in 1.5s (failed: anthropic)"
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("ERROR: git clone failed"));
+        assert!(text.contains("+ calendar"));
+        assert!(text.contains("  jo at 0123456789ab ("));
+        assert!(text.contains("- ... and 5 more"));
+    }
+}
@@ -0,0 +1,394 @@
+use crate::db::{Database, Skill};
+use crate::index::SearchResult;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Dimensionality of the dense term and document vectors.
+pub const DIMENSIONS: usize = 128;
+
+/// Number of non-zero entries in each sparse random index vector.
+const NONZEROS: usize = 8;
+
+/// Upper bound on vocabulary size kept in the model (most widespread terms win).
+const MAX_VOCABULARY: usize = 30_000;
+
+/// Constant used by reciprocal rank fusion to dampen the weight of top ranks.
+const RRF_K: f32 = 60.0;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "can", "do", "for", "from", "has", "have", "if", "in",
+    "into", "is", "it", "its", "me", "my", "no", "not", "of", "on", "or", "our", "so", "that", "the", "their",
+    "then", "there", "these", "this", "to", "use", "was", "we", "when", "which", "will", "with", "you", "your",
+];
+
+#[derive(Debug, Clone)]
+pub struct TermVector {
+    pub term: String,
+    pub idf: f32,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DocVector {
+    pub registry: String,
+    pub slug: String,
+    pub name: String,
+    pub description: String,
+    pub vector: Vec<f32>,
+}
+
+/// Random-indexing model trained on the local catalog.
+///
+/// Every skill gets a sparse random "index vector"; a term's context vector is the
+/// sum of the index vectors of the skills it appears in, so terms that occur in
+/// similar skills end up close together. Documents and queries are embedded as the
+/// idf-weighted sum of their terms' context vectors. Everything runs on CPU in a
+/// single pass over the catalog and needs no network access.
+pub struct SemanticModel {
+    terms: HashMap<String, TermVector>,
+    docs: Vec<DocVector>,
+}
+
+impl SemanticModel {
+    pub fn build(skills: &[Skill]) -> Self {
+        let tokenized: Vec<Vec<String>> = skills
+            .iter()
+            .map(|s| tokenize(&format!("{} {} {}", s.name, s.description, s.skill_md)))
+            .collect();
+
+        // Document frequency per term
+        let mut df: HashMap<&str, usize> = HashMap::new();
+        for tokens in &tokenized {
+            let mut seen: Vec<&str> = tokens.iter().map(|t| t.as_str()).collect();
+            seen.sort_unstable();
+            seen.dedup();
+            for t in seen {
+                *df.entry(t).or_insert(0) += 1;
+            }
+        }
+
+        // Terms seen only once cannot relate two skills; BM25 already covers exact matches.
+        let mut vocabulary: Vec<(&str, usize)> = df.into_iter().filter(|(_, n)| *n >= 2).collect();
+        vocabulary.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        vocabulary.truncate(MAX_VOCABULARY);
+
+        let n_docs = skills.len().max(1) as f32;
+        let mut terms: HashMap<String, TermVector> = vocabulary
+            .iter()
+            .map(|(t, n)| {
+                (
+                    t.to_string(),
+                    TermVector {
+                        term: t.to_string(),
+                        idf: (1.0 + n_docs / *n as f32).ln(),
+                        vector: vec![0.0; DIMENSIONS],
+                    },
+                )
+            })
+            .collect();
+
+        // Accumulate context vectors from each skill's random index vector
+        for (skill, tokens) in skills.iter().zip(&tokenized) {
+            let index_vector = random_index_vector(&format!("{}:{}", skill.registry, skill.slug));
+            for (term, tf) in term_frequencies(tokens) {
+                if let Some(tv) = terms.get_mut(term) {
+                    let weight = 1.0 + (tf as f32).ln();
+                    for &(pos, sign) in &index_vector {
+                        tv.vector[pos] += sign * weight;
+                    }
+                }
+            }
+        }
+        for tv in terms.values_mut() {
+            normalize(&mut tv.vector);
+        }
+
+        let mut model = Self { terms, docs: Vec::new() };
+        model.docs = skills
+            .iter()
+            .zip(&tokenized)
+            .map(|(skill, tokens)| DocVector {
+                registry: skill.registry.clone(),
+                slug: skill.slug.clone(),
+                name: skill.name.clone(),
+                description: skill.description.clone(),
+                vector: model.embed_tokens(tokens),
+            })
+            .collect();
+        model
+    }
+
+    /// Builds the model from every skill in the database and stores it there.
+    pub fn rebuild(db: &Database) -> Result<()> {
+        let skills = db.get_all_skills()?;
+        let model = Self::build(&skills);
+        db.replace_semantic_model(model.terms.values(), &model.docs)?;
+        tracing::info!(
+            "Semantic model rebuilt ({} terms, {} skills)",
+            model.terms.len(),
+            model.docs.len()
+        );
+        Ok(())
+    }
+
+    /// Loads the vectors needed to answer `query`: its terms plus every document.
+    pub fn load_for_query(db: &Database, query: &str) -> Result<Self> {
+        let mut tokens = tokenize(query);
+        tokens.sort_unstable();
+        tokens.dedup();
+        // Terms and documents must come from the same rebuild
+        db.read_snapshot(|db| {
+            let terms = db
+                .get_semantic_terms(&tokens)?
+                .into_iter()
+                .map(|t| (t.term.clone(), t))
+                .collect();
+            let docs = db.get_semantic_docs()?;
+            Ok(Self { terms, docs })
+        })
+    }
+
+    pub fn embed(&self, text: &str) -> Vec<f32> {
+        self.embed_tokens(&tokenize(text))
+    }
+
+    fn embed_tokens(&self, tokens: &[String]) -> Vec<f32> {
+        let mut vector = vec![0.0; DIMENSIONS];
+        for (term, tf) in term_frequencies(tokens) {
+            if let Some(tv) = self.terms.get(term) {
+                let weight = (1.0 + (tf as f32).ln()) * tv.idf;
+                for (v, t) in vector.iter_mut().zip(&tv.vector) {
+                    *v += weight * t;
+                }
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+
+    pub fn search(&self, query: &str, limit: usize, registry: Option<&str>) -> Vec<SearchResult> {
+        let query_vector = self.embed(query);
+        if query_vector.iter().all(|v| *v == 0.0) {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(f32, &DocVector)> = self
+            .docs
+            .iter()
+            .filter(|d| registry.is_none_or(|r| d.registry == r))
+            .map(|d| (cosine(&query_vector, &d.vector), d))
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(score, d)| SearchResult {
+                slug: d.slug.clone(),
+                name: d.name.clone(),
+                description: d.description.clone(),
+                registry: d.registry.clone(),
+                score,
+            })
+            .collect()
+    }
+}
+
+/// Combines several ranked result lists with reciprocal rank fusion.
+///
+/// Each result scores `sum(1 / (k + rank))` over the lists it appears in, so skills
+/// ranked well by both BM25 and the semantic model float to the top.
+pub fn reciprocal_rank_fusion(lists: &[Vec<SearchResult>], limit: usize) -> Vec<SearchResult> {
+    let mut fused: HashMap<String, SearchResult> = HashMap::new();
+    for list in lists {
+        for (rank, result) in list.iter().enumerate() {
+            let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+            fused
+                .entry(result.unique_key())
+                .and_modify(|r| r.score += contribution)
+                .or_insert_with(|| SearchResult {
+                    score: contribution,
+                    ..result.clone()
+                });
+        }
+    }
+
+    let mut results: Vec<SearchResult> = fused.into_values().collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.unique_key().cmp(&b.unique_key())));
+    results.truncate(limit);
+    results
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() >= 2 && !t.chars().all(|c| c.is_ascii_digit()))
+        .map(|t| stem(&t.to_lowercase()))
+        .filter(|t| !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+/// Very light stemming so that "slides" and "slide" share a vector.
+fn stem(token: &str) -> String {
+    if token.len() > 4 && token.ends_with("ies") {
+        format!("{}y", &token[..token.len() - 3])
+    } else if token.len() > 3 && token.ends_with('s') && !token.ends_with("ss") {
+        token[..token.len() - 1].to_string()
+    } else {
+        token.to_string()
+    }
+}
+
+fn term_frequencies(tokens: &[String]) -> HashMap<&str, usize> {
+    let mut tf = HashMap::new();
+    for t in tokens {
+        *tf.entry(t.as_str()).or_insert(0) += 1;
+    }
+    tf
+}
+
+/// Deterministic sparse ternary vector derived from a key.
+fn random_index_vector(key: &str) -> Vec<(usize, f32)> {
+    let mut state = fnv1a(key.as_bytes());
+    (0..NONZEROS)
+        .map(|_| {
+            state = splitmix64(state);
+            let pos = (state % DIMENSIONS as u64) as usize;
+            let sign = if (state >> 32) & 1 == 0 { 1.0 } else { -1.0 };
+            (pos, sign)
+        })
+        .collect()
+}
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in v.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub fn vector_to_blob(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_skill(slug: &str, description: &str, skill_md: &str) -> Skill {
+        Skill {
+            id: 0,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            registry: "clawdhub".to_string(),
+            description: description.to_string(),
+            skill_md: skill_md.to_string(),
+            github_url: format!("https://github.com/test/{}", slug),
+            version: None,
+            stars: 0,
+            trusted: false,
+            updated_at: 1234567890,
+            commit: None,
+            permalink: None,
+        }
+    }
+
+    fn catalog() -> Vec<Skill> {
+        vec![
+            create_test_skill("pptx", "Create presentation decks", "Build slides and presentation decks with themes and layouts"),
+            create_test_skill("deck-design", "Design polished presentation", "Improve presentation layouts, theme colors and typography"),
+            create_test_skill("slide-notes", "Speaker notes for slides", "Write speaker notes for each slide of a presentation"),
+            create_test_skill("postgres", "Query databases", "Run SQL queries against postgres database tables"),
+            create_test_skill("sqlite", "Local database", "Inspect sqlite database tables and run SQL"),
+        ]
+    }
+
+    #[test]
+    fn test_tokenize_stems_and_drops_stopwords() {
+        assert_eq!(tokenize("Make my Slides look nicer!"), vec!["make", "slide", "look", "nicer"]);
+        assert_eq!(tokenize("libraries 2024"), vec!["library"]);
+    }
+
+    #[test]
+    fn test_semantic_search_matches_related_terms() {
+        let model = SemanticModel::build(&catalog());
+        // "theme" never appears in the slide-notes skill but co-occurs with presentation skills
+        let results = model.search("theme for my deck", 5, None);
+        assert!(!results.is_empty());
+        assert!(["pptx", "deck-design", "slide-notes"].contains(&results[0].slug.as_str()));
+        assert!(results.iter().all(|r| r.slug != "postgres" || r.score < results[0].score));
+    }
+
+    #[test]
+    fn test_semantic_search_unknown_terms() {
+        let model = SemanticModel::build(&catalog());
+        assert!(model.search("zzqx", 5, None).is_empty());
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let result = |slug: &str| SearchResult {
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            description: String::new(),
+            registry: "clawdhub".to_string(),
+            score: 1.0,
+        };
+        let bm25 = vec![result("a"), result("b"), result("c")];
+        let semantic = vec![result("b"), result("d")];
+
+        let fused = reciprocal_rank_fusion(&[bm25, semantic], 10);
+        assert_eq!(fused[0].slug, "b");
+        assert_eq!(fused.len(), 4);
+    }
+
+    #[test]
+    fn test_model_roundtrip_through_database() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        for skill in catalog() {
+            db.upsert_skill(&skill).unwrap();
+        }
+
+        SemanticModel::rebuild(&db).unwrap();
+        let model = SemanticModel::load_for_query(&db, "sql database").unwrap();
+        let results = model.search("sql database", 2, None);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.slug == "postgres" || r.slug == "sqlite"));
+    }
+
+    #[test]
+    fn test_vector_blob_roundtrip() {
+        let v = vec![0.5, -1.25, 3.0];
+        assert_eq!(blob_to_vector(&vector_to_blob(&v)), v);
+    }
+}
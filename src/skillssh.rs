@@ -1,10 +1,41 @@
-use crate::db::{Database, Skill};
+use crate::db::Skill;
+use crate::forge::{Forge, RepoLinks};
+use crate::git;
+use crate::github::{find_skill_dirs, parse_skill_frontmatter};
+use crate::metrics::MetricKind;
+use crate::report::SkillError;
+use crate::source::{SkillSource, SourceContext, SourceSink};
+use crate::sync::MetricUpdate;
 use anyhow::Result;
-use serde::Deserialize;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinSet;
 
 const API_BASE: &str = "https://skills.sh";
 
+/// How deep to look for SKILL.md files inside a source repository.
+const MAX_SKILL_DEPTH: usize = 4;
+
+/// Results requested per page; a full page means there may be more behind it.
+const PAGE_LIMIT: usize = 100;
+
+/// Longest query prefix the crawler expands to when offset paging stops yielding new skills.
+const MAX_PREFIX_LEN: usize = 3;
+
+/// Stop crawling after this many consecutive requests that surface no new skills.
+const MAX_STALE_REQUESTS: usize = 60;
+
+/// Save the crawl position to `sync_state` every this many requests.
+const CHECKPOINT_EVERY: usize = 10;
+
+/// Give up on the crawl (keeping its cursor) after this many consecutive failed requests.
+const MAX_FAILED_REQUESTS: usize = 3;
+
+const QUERY_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+
 #[derive(Debug, Deserialize)]
 struct SearchResponse {
     skills: Vec<SkillsShSkill>,
@@ -12,7 +43,7 @@ struct SearchResponse {
     count: usize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct SkillsShSkill {
     id: String,
     name: String,
@@ -21,55 +52,315 @@ struct SkillsShSkill {
     top_source: Option<String>,
 }
 
-pub async fn sync_skillssh(db: &mut Database) -> Result<()> {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CrawlRequest {
+    query: String,
+    offset: usize,
+}
+
+/// Crawl progress, saved as the `skillssh` sync cursor so an interrupted crawl resumes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CrawlState {
+    pending: VecDeque<CrawlRequest>,
+    skills: BTreeMap<String, SkillsShSkill>,
+}
+
+impl CrawlState {
+    fn seeded() -> Self {
+        let mut pending = VecDeque::new();
+        pending.push_back(CrawlRequest { query: String::new(), offset: 0 });
+        for c in QUERY_ALPHABET.chars() {
+            pending.push_back(CrawlRequest { query: c.to_string(), offset: 0 });
+        }
+        Self { pending, skills: BTreeMap::new() }
+    }
+
+    /// Records a page of results and schedules follow-up requests. Returns how many
+    /// previously unseen skills the page contained.
+    fn record_page(&mut self, request: &CrawlRequest, page: Vec<SkillsShSkill>) -> usize {
+        let full = page.len() >= PAGE_LIMIT;
+        let mut new_ids = 0;
+        for skill in page {
+            if self.skills.insert(skill.id.clone(), skill).is_none() {
+                new_ids += 1;
+            }
+        }
+
+        if full && new_ids > 0 {
+            self.pending.push_back(CrawlRequest {
+                query: request.query.clone(),
+                offset: request.offset + PAGE_LIMIT,
+            });
+        } else if full && request.offset > 0 && !request.query.is_empty() && request.query.len() < MAX_PREFIX_LEN {
+            // The API ignored the offset; narrow the query instead
+            for c in QUERY_ALPHABET.chars() {
+                self.pending.push_back(CrawlRequest {
+                    query: format!("{}{}", request.query, c),
+                    offset: 0,
+                });
+            }
+        }
+        new_ids
+    }
+}
+
+/// A shallow clone of a skills.sh `topSource` repository. Its history isn't fetched:
+/// these are third-party repositories, often large, so skills from them carry no
+/// last-commit info.
+struct SourceCheckout {
+    repo_dir: PathBuf,
+    links: RepoLinks,
+    skill_dirs: Vec<PathBuf>,
+}
+
+impl SourceCheckout {
+    fn fetch(source: &str, sources_dir: &Path) -> Result<Self> {
+        let repo_dir = sources_dir.join(source.replace('/', "__"));
+        let repo_url = github_repo_url(source);
+        git::clone_or_pull(&repo_url, &repo_dir, git::DEFAULT_GIT_TIMEOUT)?;
+        let links = RepoLinks::new(
+            &repo_url,
+            Some(Forge::GitHub),
+            &git::current_branch(&repo_dir)?,
+            git::head_commit(&repo_dir).ok().as_deref(),
+        );
+        let skill_dirs = find_skill_dirs(&repo_dir, MAX_SKILL_DEPTH);
+        Ok(Self {
+            repo_dir,
+            links,
+            skill_dirs,
+        })
+    }
+
+    /// Locates the directory of a skill by directory name, falling back to the
+    /// `name` declared in each SKILL.md's frontmatter.
+    fn find_skill(&self, skill_id: &str, name: &str) -> Option<&Path> {
+        let by_dir = self.skill_dirs.iter().find(|d| {
+            d.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.eq_ignore_ascii_case(skill_id) || n.eq_ignore_ascii_case(name))
+        });
+        by_dir
+            .or_else(|| {
+                self.skill_dirs.iter().find(|d| {
+                    std::fs::read_to_string(d.join("SKILL.md")).is_ok_and(|md| {
+                        let (fm_name, _, _) = parse_skill_frontmatter(&md);
+                        fm_name.eq_ignore_ascii_case(skill_id) || fm_name.eq_ignore_ascii_case(name)
+                    })
+                })
+            })
+            .map(|d| d.as_path())
+    }
+}
+
+/// The skills.sh catalog: an HTTP API listing skills, whose SKILL.md files live in
+/// the GitHub repos behind each entry.
+pub struct SkillsShSource;
+
+#[async_trait]
+impl SkillSource for SkillsShSource {
+    fn name(&self) -> &str {
+        "skillssh"
+    }
+
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+        sync_skillssh(ctx, sink).await
+    }
+
+    fn describe_cursor(&self, cursor: &str) -> Option<String> {
+        let state: CrawlState = serde_json::from_str(cursor).ok()?;
+        Some(format!(
+            "crawl resumes with {} queued requests, {} skills seen",
+            state.pending.len(),
+            state.skills.len()
+        ))
+    }
+}
+
+/// Crawls skills.sh, clones the source repos behind its entries and sends the
+/// resulting skills, with their install counts, to the writer.
+///
+/// `ctx.cursor` is the saved crawl state of an interrupted run; fetches of source
+/// repos share the sync-wide limiter with the other registries. If the API keeps
+/// failing, the skills listed so far are still written and the crawl resumes on the
+/// next sync.
+async fn sync_skillssh(ctx: &SourceContext, sink: &SourceSink) -> Result<()> {
+    let (http, limiter) = (&ctx.http, &ctx.limiter);
+    let permit = limiter.acquire().await?;
     tracing::info!("Syncing skills.sh registry...");
 
-    let client = reqwest::Client::builder()
-        .user_agent("skill-search/0.1")
-        .build()?;
-
-    let mut total = 0;
-    let queries = ["", "a", "e", "i", "o", "u", "s", "t", "n", "r", "code", "docker", "git", "api", "test", "debug", "python", "rust", "javascript", "typescript"];
-
-    for query in queries {
-        let url = format!("{}/api/search?q={}&limit=100", API_BASE, query);
-        
-        match client.get(&url).send().await {
-            Ok(resp) if resp.status().is_success() => {
-                if let Ok(data) = resp.json::<SearchResponse>().await {
-                    for skill in data.skills {
-                        if let Err(e) = upsert_skillssh_skill(db, &skill) {
-                            tracing::debug!("Failed to upsert skill {}: {}", skill.id, e);
-                        } else {
-                            total += 1;
-                        }
+    let mut state = match ctx.cursor.as_deref().map(serde_json::from_str::<CrawlState>) {
+        Some(Ok(state)) => {
+            tracing::info!(
+                "Resuming skills.sh crawl ({} queued requests, {} skills seen)",
+                state.pending.len(),
+                state.skills.len()
+            );
+            state
+        }
+        _ => CrawlState::seeded(),
+    };
+    let mut requests = 0;
+    let mut stale = 0;
+    let mut failed: Vec<CrawlRequest> = Vec::new();
+    let mut interrupted = false;
+    while let Some(request) = state.pending.pop_front() {
+        let url = format!(
+            "{}/api/search?q={}&limit={}&offset={}",
+            API_BASE, request.query, PAGE_LIMIT, request.offset
+        );
+
+        let new_ids = match http.get_json::<SearchResponse>(&url).await {
+            Ok(data) => {
+                failed.clear();
+                state.record_page(&request, data.skills)
+            }
+            Err(e) => {
+                tracing::warn!("skills.sh request failed for query '{}': {}", request.query, e);
+                failed.push(request);
+                if failed.len() >= MAX_FAILED_REQUESTS {
+                    // Retry the whole failed run first when the crawl resumes
+                    for request in failed.drain(..).rev() {
+                        state.pending.push_front(request);
                     }
+                    interrupted = true;
+                    break;
                 }
+                0
             }
-            Ok(resp) => {
-                tracing::debug!("skills.sh API error for query '{}': {}", query, resp.status());
+        };
+
+        stale = if new_ids > 0 { 0 } else { stale + 1 };
+        requests += 1;
+        if requests % CHECKPOINT_EVERY == 0 {
+            save_cursor(sink, &state).await?;
+            tracing::debug!("skills.sh crawl: {} requests, {} skills", requests, state.skills.len());
+        }
+        if stale >= MAX_STALE_REQUESTS {
+            tracing::debug!("No new skills.sh skills in {} requests, stopping crawl", stale);
+            state.pending.clear();
+        }
+    }
+    save_cursor(sink, &state).await?;
+    drop(permit);
+    let listed = state.skills;
+    if interrupted {
+        tracing::warn!(
+            "skills.sh crawl interrupted after {} requests; {} skills kept, resuming next sync",
+            requests,
+            listed.len()
+        );
+    } else {
+        tracing::info!("Found {} skills on skills.sh in {} requests", listed.len(), requests);
+    }
+
+    // Clone each distinct source repository once to read the real SKILL.md files
+    let sources_dir = ctx.repos_dir.join("skillssh");
+    std::fs::create_dir_all(&sources_dir)?;
+    let mut sources: Vec<String> = listed
+        .values()
+        .filter_map(|s| s.top_source.clone())
+        .filter(|s| is_github_source(s))
+        .collect();
+    sources.sort();
+    sources.dedup();
+
+    let mut fetches = JoinSet::new();
+    for source in sources {
+        let (limiter, sources_dir) = (limiter.clone(), sources_dir.clone());
+        fetches.spawn(async move {
+            let _permit = limiter.acquire_owned().await?;
+            let fetched = {
+                let source = source.clone();
+                tokio::task::spawn_blocking(move || SourceCheckout::fetch(&source, &sources_dir)).await?
+            };
+            anyhow::Ok((source, fetched))
+        });
+    }
+    let mut checkouts: HashMap<String, SourceCheckout> = HashMap::new();
+    while let Some(joined) = fetches.join_next().await {
+        match joined? {
+            Ok((source, Ok(checkout))) => {
+                checkouts.insert(source, checkout);
+            }
+            Ok((source, Err(e))) => tracing::warn!("Failed to fetch skills.sh source {}: {}", source, e),
+            Err(e) => tracing::warn!("Failed to fetch skills.sh source: {}", e),
+        }
+    }
+
+    let mut skills = Vec::with_capacity(listed.len());
+    let mut failed = Vec::new();
+    let mut metrics = Vec::with_capacity(listed.len());
+    for skill in listed.values() {
+        let checkout = skill.top_source.as_deref().and_then(|source| checkouts.get(source));
+        match build_skill(skill, checkout) {
+            Ok(built) => {
+                metrics.push(MetricUpdate {
+                    slug: built.slug.clone(),
+                    kind: MetricKind::Installs,
+                    value: skill.installs,
+                });
+                skills.push(built);
             }
             Err(e) => {
-                tracing::debug!("skills.sh request failed for query '{}': {}", query, e);
+                tracing::debug!("Failed to read skill {}: {}", skill.id, e);
+                failed.push(SkillError {
+                    slug: skill_slug(&skill.id),
+                    error: e.to_string(),
+                });
             }
         }
     }
 
-    tracing::info!("Synced {} skills from skills.sh", total);
+    tracing::info!(
+        "Found {} skills on skills.sh ({} source repos)",
+        skills.len(),
+        checkouts.len()
+    );
 
-    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
-    db.set_last_sync("skillssh", now, None)?;
+    sink.skills(skills, failed).await?;
+    sink.metrics(API_BASE, metrics).await?;
+    if interrupted {
+        anyhow::bail!("skills.sh API unavailable, crawl incomplete");
+    }
+    sink.complete(None).await?;
+    sink.cursor(None).await
+}
+
+async fn save_cursor(sink: &SourceSink, state: &CrawlState) -> Result<()> {
+    sink.cursor(Some(serde_json::to_string(state)?)).await
+}
 
-    Ok(())
+fn github_repo_url(source: &str) -> String {
+    format!("https://github.com/{}.git", source)
+}
+
+/// `topSource` values are `owner/repo` GitHub paths.
+fn is_github_source(source: &str) -> bool {
+    let mut parts = source.split('/');
+    let valid = |p: Option<&str>| {
+        p.is_some_and(|p| !p.is_empty() && p.chars().all(|c| c.is_alphanumeric() || "-_.".contains(c)))
+    };
+    valid(parts.next()) && valid(parts.next()) && parts.next().is_none()
 }
 
-fn upsert_skillssh_skill(db: &mut Database, skill: &SkillsShSkill) -> Result<()> {
+/// skills.sh ids are `owner/repo/skill` paths; slugs can't contain `/`.
+fn skill_slug(id: &str) -> String {
+    id.replace('/', "__")
+}
+
+fn build_skill(skill: &SkillsShSkill, checkout: Option<&SourceCheckout>) -> Result<Skill> {
     let source = skill.top_source.as_deref().unwrap_or("");
-    
-    let (github_url, description) = if !source.is_empty() {
-        let skill_id = skill.id.split('/').last().unwrap_or(&skill.id);
+    let skill_id = skill.id.split('/').next_back().unwrap_or(&skill.id);
+
+    let mut name = skill.name.clone();
+    let mut skill_md = String::new();
+    let mut version = None;
+    let mut permalink = None;
+    // Without a checkout the skill's directory and branch are unknown; link the repo
+    let (mut github_url, mut description) = if !source.is_empty() {
         (
-            format!("https://github.com/{}/tree/main/skills/{}", source, skill_id),
+            github_repo_url(source).trim_end_matches(".git").to_string(),
             format!("From {}", source),
         )
     } else {
@@ -79,34 +370,155 @@ fn upsert_skillssh_skill(db: &mut Database, skill: &SkillsShSkill) -> Result<()>
         )
     };
 
-    let slug = skill.id.replace('/', "__");
+    match checkout.and_then(|c| c.find_skill(skill_id, &skill.name).map(|dir| (c, dir))) {
+        Some((checkout, dir)) => {
+            skill_md = std::fs::read_to_string(dir.join("SKILL.md"))?;
+            let (fm_name, fm_description, fm_version) = parse_skill_frontmatter(&skill_md);
+            if !fm_name.is_empty() {
+                name = fm_name;
+            }
+            if !fm_description.is_empty() {
+                description = fm_description;
+            }
+            version = fm_version;
+            let rel_path = dir.strip_prefix(&checkout.repo_dir).unwrap_or(dir);
+            github_url = checkout.links.tree_url(rel_path);
+            permalink = checkout.links.permalink(rel_path);
+        }
+        None if checkout.is_some() => {
+            tracing::debug!("No SKILL.md found for {} in {}", skill.id, source);
+        }
+        None => {}
+    }
+
+    let slug = skill_slug(&skill.id);
 
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
 
-    let db_skill = Skill {
+    Ok(Skill {
         id: 0,
         slug,
-        name: skill.name.clone(),
+        name,
         registry: "skillssh".to_string(),
         description,
-        skill_md: String::new(),
+        skill_md,
         github_url,
-        version: None,
-        stars: skill.installs,
+        version,
+        stars: 0,
         trusted: false,
         updated_at: now,
-    };
-
-    db.upsert_skill(&db_skill)?;
-    Ok(())
+        commit: None,
+        permalink,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use tempfile::tempdir;
+
     #[test]
     fn test_api_base_url() {
         assert_eq!(API_BASE, "https://skills.sh");
     }
+
+    fn listed(id: &str) -> SkillsShSkill {
+        SkillsShSkill {
+            id: id.to_string(),
+            name: id.to_string(),
+            installs: 0,
+            top_source: None,
+        }
+    }
+
+    #[test]
+    fn test_crawl_pages_while_new_ids_appear() {
+        let mut state = CrawlState { pending: VecDeque::new(), skills: BTreeMap::new() };
+        let request = CrawlRequest { query: "a".to_string(), offset: 0 };
+        let page: Vec<_> = (0..PAGE_LIMIT).map(|i| listed(&format!("s{}", i))).collect();
+
+        assert_eq!(state.record_page(&request, page.clone()), PAGE_LIMIT);
+        assert_eq!(state.pending.len(), 1);
+        assert_eq!(state.pending[0].offset, PAGE_LIMIT);
+
+        // Same page again: the API ignored the offset, so narrow the query instead
+        let next = state.pending.pop_front().unwrap();
+        assert_eq!(state.record_page(&next, page), 0);
+        assert_eq!(state.pending.len(), QUERY_ALPHABET.len());
+        assert_eq!(state.pending[0].query, "aa");
+        assert_eq!(state.skills.len(), PAGE_LIMIT);
+    }
+
+    #[test]
+    fn test_crawl_stops_on_short_page() {
+        let mut state = CrawlState::default();
+        let request = CrawlRequest { query: "zz".to_string(), offset: 0 };
+        assert_eq!(state.record_page(&request, vec![listed("a"), listed("b")]), 2);
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn test_crawl_state_roundtrip() {
+        let mut state = CrawlState::seeded();
+        state.skills.insert("a".to_string(), listed("a"));
+        let restored: CrawlState = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert_eq!(restored.pending.len(), QUERY_ALPHABET.len() + 1);
+        assert_eq!(restored.skills.len(), 1);
+
+        let cursor = serde_json::to_string(&state).unwrap();
+        let described = SkillsShSource.describe_cursor(&cursor).unwrap();
+        assert_eq!(described, format!("crawl resumes with {} queued requests, 1 skills seen", QUERY_ALPHABET.len() + 1));
+        assert!(SkillsShSource.describe_cursor("garbage").is_none());
+    }
+
+    #[test]
+    fn test_is_github_source() {
+        assert!(is_github_source("vercel-labs/agent-skills"));
+        assert!(!is_github_source("vercel-labs"));
+        assert!(!is_github_source("a/b/c"));
+        assert!(!is_github_source("owner/repo;rm -rf"));
+    }
+
+    #[test]
+    fn test_build_skill_reads_skill_md_from_checkout() {
+        let dir = tempdir().unwrap();
+        let repo_dir = dir.path().join("owner__repo");
+        let skill_dir = repo_dir.join("skills").join("react-best-practices");
+        std::fs::create_dir_all(&skill_dir).unwrap();
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            "---\nname: react-best-practices\ndescription: Write idiomatic React\n---\n# React\n",
+        )
+        .unwrap();
+
+        let checkout = SourceCheckout {
+            skill_dirs: find_skill_dirs(&repo_dir, MAX_SKILL_DEPTH),
+            repo_dir,
+            links: RepoLinks::new("https://github.com/owner/repo.git", None, "canary", Some("abc123")),
+        };
+        let entry = SkillsShSkill {
+            id: "owner/repo/react-best-practices".to_string(),
+            name: "react-best-practices".to_string(),
+            installs: 42,
+            top_source: Some("owner/repo".to_string()),
+        };
+
+        let skill = build_skill(&entry, Some(&checkout)).unwrap();
+        assert_eq!(skill.slug, "owner__repo__react-best-practices");
+        assert_eq!(skill.description, "Write idiomatic React");
+        assert!(skill.skill_md.contains("# React"));
+        assert_eq!(
+            skill.github_url,
+            "https://github.com/owner/repo/tree/canary/skills/react-best-practices"
+        );
+        assert_eq!(
+            skill.permalink.as_deref(),
+            Some("https://github.com/owner/repo/tree/abc123/skills/react-best-practices")
+        );
+
+        // Without a checkout only the repository is known
+        let unfetched = build_skill(&entry, None).unwrap();
+        assert_eq!(unfetched.github_url, "https://github.com/owner/repo");
+    }
 }
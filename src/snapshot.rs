@@ -0,0 +1,349 @@
+use crate::db::{Database, SyncState};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File names a bundled catalog snapshot is looked up under: an archive written by
+/// `snapshot export`, or a bare copy of another machine's `skills.db`.
+pub const SNAPSHOT_FILES: &[&str] = &["skills-snapshot.tar.gz", "skills-snapshot.db"];
+
+/// Environment variable naming a snapshot to bootstrap from instead of the bundled one.
+pub const SNAPSHOT_ENV: &str = "SKILL_SEARCH_SNAPSHOT";
+
+const FORMAT: &str = "safe-skill-search-snapshot";
+/// Bumped when a snapshot can no longer be read by older builds.
+const VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const DATABASE: &str = "skills.db";
+const REPOS: &str = "repos";
+
+/// Describes a snapshot archive; stored as `manifest.json` ahead of the files it lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub created_at: i64,
+    /// Name and version of the build that wrote the snapshot.
+    pub generator: String,
+    pub skills: usize,
+    /// Where each registry's last sync left it on the exporting machine.
+    pub registries: Vec<SyncState>,
+    /// SHA-256 of every other file in the archive, by path.
+    pub files: BTreeMap<String, String>,
+}
+
+/// What `import` loaded.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    pub skills: usize,
+    /// When the snapshot was exported; unknown for a bare database copy.
+    pub created_at: Option<i64>,
+    /// Files restored under `repos/`.
+    pub skill_files: usize,
+}
+
+/// Writes the catalog to a `.tar.gz` snapshot at `dest`: a manifest, the catalog
+/// database (skills with their metrics, scan findings and sync state), and with
+/// `with_skills` the checked-out skill directories under `repos/`, minus git metadata.
+pub fn export(db: &Database, data_dir: &Path, repos_dir: &Path, dest: &Path, with_skills: bool) -> Result<Manifest> {
+    let staging = data_dir.join(".snapshot.exporting");
+    reset_dir(&staging)?;
+    let result = write_archive(db, repos_dir, dest, with_skills, &staging);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn write_archive(db: &Database, repos_dir: &Path, dest: &Path, with_skills: bool, staging: &Path) -> Result<Manifest> {
+    let database = staging.join(DATABASE);
+    let skills = db.export_catalog(&database)?;
+    if skills == 0 {
+        anyhow::bail!("nothing to export: the catalog is empty, run sync first");
+    }
+    let mut files = vec![(DATABASE.to_string(), database)];
+    if with_skills && repos_dir.is_dir() {
+        collect_files(repos_dir, REPOS, &mut files)?;
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+        generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        skills,
+        registries: db.get_sync_states()?,
+        files: files
+            .iter()
+            .map(|(name, path)| Ok((name.clone(), sha256_file(path)?)))
+            .collect::<Result<_>>()?,
+    };
+
+    // Written beside the destination and renamed, so a failed export never leaves half a snapshot
+    let partial = dest.with_file_name(format!(
+        ".{}.partial",
+        dest.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+    ));
+    let written = (|| -> Result<()> {
+        let file = File::create(&partial).with_context(|| format!("creating {}", partial.display()))?;
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+        let json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at as u64);
+        builder.append_data(&mut header, MANIFEST, json.as_slice())?;
+        for (name, path) in &files {
+            builder.append_path_with_name(path, name)?;
+        }
+        builder.into_inner()?.finish()?.sync_all()?;
+        std::fs::rename(&partial, dest)?;
+        Ok(())
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    written.with_context(|| format!("writing snapshot {}", dest.display()))?;
+    Ok(manifest)
+}
+
+/// Replaces the catalog with a snapshot's, after checking every file against the
+/// manifest, and restores any skill directories it carries into `repos_dir`. A bare
+/// `skills.db` copy is loaded as is. The caller rebuilds the search index.
+pub fn import(db: &mut Database, data_dir: &Path, repos_dir: &Path, file: &Path) -> Result<Imported> {
+    let mut magic = [0u8; 16];
+    let is_database = File::open(file)
+        .with_context(|| format!("opening snapshot {}", file.display()))?
+        .read_exact(&mut magic)
+        .is_ok()
+        && &magic == b"SQLite format 3\0";
+    if is_database {
+        return Ok(Imported {
+            skills: db.import_catalog(file)?,
+            created_at: None,
+            skill_files: 0,
+        });
+    }
+
+    let staging = data_dir.join(".snapshot.importing");
+    reset_dir(&staging)?;
+    let result = read_archive(db, repos_dir, file, &staging);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn read_archive(db: &mut Database, repos_dir: &Path, file: &Path, staging: &Path) -> Result<Imported> {
+    tar::Archive::new(flate2::read::GzDecoder::new(File::open(file)?))
+        .unpack(staging)
+        .with_context(|| format!("{} is not a snapshot archive", file.display()))?;
+    let manifest: Manifest = serde_json::from_slice(
+        &std::fs::read(staging.join(MANIFEST))
+            .with_context(|| format!("{} has no {}, so it is not a snapshot", file.display(), MANIFEST))?,
+    )
+    .with_context(|| format!("invalid {} in {}", MANIFEST, file.display()))?;
+    if manifest.format != FORMAT {
+        anyhow::bail!("{} is a '{}' archive, not a skill-search snapshot", file.display(), manifest.format);
+    }
+    if manifest.version > VERSION {
+        anyhow::bail!(
+            "snapshot format {} is newer than this build supports ({}); upgrade {} to import it",
+            manifest.version,
+            VERSION,
+            env!("CARGO_PKG_NAME")
+        );
+    }
+
+    let mut files = Vec::new();
+    collect_files(staging, "", &mut files)?;
+    for (name, path) in files.iter().filter(|(name, _)| name != MANIFEST) {
+        match manifest.files.get(name) {
+            Some(expected) if *expected == sha256_file(path)? => {}
+            Some(_) => anyhow::bail!("snapshot is corrupt: checksum mismatch for {}", name),
+            None => anyhow::bail!("snapshot is corrupt: {} is not in the manifest", name),
+        }
+    }
+    if let Some(missing) = manifest.files.keys().find(|name| !files.iter().any(|(n, _)| n == *name)) {
+        anyhow::bail!("snapshot is incomplete: {} is missing", missing);
+    }
+
+    let skills = db.import_catalog(&staging.join(DATABASE))?;
+    let restored = staging.join(REPOS);
+    if restored.is_dir() {
+        std::fs::create_dir_all(repos_dir)?;
+        for entry in std::fs::read_dir(&restored)? {
+            let entry = entry?;
+            let dest = repos_dir.join(entry.file_name());
+            if dest.exists() {
+                std::fs::remove_dir_all(&dest)?;
+            }
+            std::fs::rename(entry.path(), &dest)?;
+        }
+    }
+    Ok(Imported {
+        skills,
+        created_at: Some(manifest.created_at),
+        skill_files: manifest.files.len() - 1,
+    })
+}
+
+/// Finds a catalog snapshot to load on first launch: the file named by
+/// `SKILL_SEARCH_SNAPSHOT`, else one of `SNAPSHOT_FILES` in the data directory or next
+/// to the executable (where a package would bundle it).
+pub fn find_bootstrap(data_dir: &Path) -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SNAPSHOT_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let bundled = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    std::iter::once(data_dir.to_path_buf())
+        .chain(bundled)
+        .flat_map(|dir| SNAPSHOT_FILES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Lists the regular files under `dir` as `(archive path, path)` pairs, skipping git
+/// metadata, symlinks, and hidden staging directories at the top level.
+fn collect_files(dir: &Path, prefix: &str, out: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == ".git" || (name.starts_with('.') && prefix == REPOS) {
+            continue;
+        }
+        let archived = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), &archived, out)?;
+        } else if file_type.is_file() {
+            out.push((archived, entry.path()));
+        }
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn reset_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Skill;
+    use tempfile::tempdir;
+
+    fn skill(slug: &str) -> Skill {
+        Skill {
+            id: 0,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            registry: "anthropic".to_string(),
+            description: format!("The {} skill", slug),
+            skill_md: format!("# {}", slug),
+            github_url: format!("https://github.com/anthropics/skills/tree/main/skills/{}", slug),
+            version: None,
+            stars: 0,
+            trusted: true,
+            updated_at: 1234567890,
+            commit: None,
+            permalink: None,
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let source = tempdir().unwrap();
+        let db = Database::open(&source.path().join("skills.db")).unwrap();
+        db.upsert_skill(&skill("pdf")).unwrap();
+        db.set_last_sync("anthropic", 1700000000, None).unwrap();
+        let skill_dir = source.path().join("repos/anthropic/skills/pdf");
+        std::fs::create_dir_all(&skill_dir).unwrap();
+        std::fs::write(skill_dir.join("SKILL.md"), "# PDF").unwrap();
+        std::fs::create_dir_all(source.path().join("repos/anthropic/.git")).unwrap();
+        std::fs::write(source.path().join("repos/anthropic/.git/HEAD"), "ref: refs/heads/main").unwrap();
+        std::fs::create_dir_all(source.path().join("repos/.jo.cloning")).unwrap();
+
+        let file = source.path().join("catalog.tar.gz");
+        let manifest = export(&db, source.path(), &source.path().join("repos"), &file, true).unwrap();
+        assert_eq!(manifest.skills, 1);
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            ["repos/anthropic/skills/pdf/SKILL.md", "skills.db"]
+        );
+        assert!(!source.path().join(".snapshot.exporting").exists());
+
+        let target = tempdir().unwrap();
+        let mut imported_db = Database::open(&target.path().join("skills.db")).unwrap();
+        let repos = target.path().join("repos");
+        let imported = import(&mut imported_db, target.path(), &repos, &file).unwrap();
+        assert_eq!((imported.skills, imported.skill_files), (1, 1));
+        assert_eq!(imported.created_at, Some(manifest.created_at));
+        assert!(imported_db.get_skill("anthropic", "pdf").unwrap().is_some());
+        assert_eq!(imported_db.get_sync_states().unwrap()[0].last_sync, Some(1700000000));
+        assert!(repos.join("anthropic/skills/pdf/SKILL.md").exists());
+        assert!(!repos.join("anthropic/.git").exists());
+        assert!(!target.path().join(".snapshot.importing").exists());
+    }
+
+    #[test]
+    fn test_import_rejects_tampered_snapshot() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("skills.db")).unwrap();
+        db.upsert_skill(&skill("pdf")).unwrap();
+        let repos = dir.path().join("repos");
+        std::fs::create_dir_all(repos.join("anthropic/pdf")).unwrap();
+        std::fs::write(repos.join("anthropic/pdf/SKILL.md"), "# PDF").unwrap();
+        let file = dir.path().join("catalog.tar.gz");
+        export(&db, dir.path(), &repos, &file, true).unwrap();
+
+        // Repack with one file changed but the original manifest
+        let unpacked = dir.path().join("unpacked");
+        tar::Archive::new(flate2::read::GzDecoder::new(File::open(&file).unwrap()))
+            .unpack(&unpacked)
+            .unwrap();
+        std::fs::write(unpacked.join("repos/anthropic/pdf/SKILL.md"), "# PDF, now with a payload").unwrap();
+        let tampered = dir.path().join("tampered.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tampered).unwrap(),
+            flate2::Compression::default(),
+        ));
+        builder.append_dir_all(".", &unpacked).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let target = tempdir().unwrap();
+        let mut target_db = Database::open(&target.path().join("skills.db")).unwrap();
+        let err = import(&mut target_db, target.path(), &target.path().join("repos"), &tampered)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("checksum mismatch for repos/anthropic/pdf/SKILL.md"), "{}", err);
+        assert!(target_db.needs_initial_sync().unwrap());
+        assert!(!target.path().join("repos").exists());
+
+        let bogus = dir.path().join("bogus.tar.gz");
+        std::fs::write(&bogus, "not a snapshot").unwrap();
+        assert!(import(&mut target_db, target.path(), &target.path().join("repos"), &bogus).is_err());
+    }
+
+    #[test]
+    fn test_import_bare_database() {
+        let dir = tempdir().unwrap();
+        let copy = dir.path().join("copy.db");
+        Database::open(&copy).unwrap().upsert_skill(&skill("pdf")).unwrap();
+
+        let mut db = Database::open(&dir.path().join("skills.db")).unwrap();
+        let imported = import(&mut db, dir.path(), &dir.path().join("repos"), &copy).unwrap();
+        assert_eq!((imported.skills, imported.created_at), (1, None));
+    }
+}
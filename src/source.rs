@@ -0,0 +1,177 @@
+use crate::db::Skill;
+use crate::github::{ClawdhubSource, GitSource, MetricsApi, Registry, RegistryKind};
+use crate::http::HttpClient;
+use crate::local::LocalSource;
+use crate::plugin::PluginSource;
+use crate::report::SkillError;
+use crate::skillssh::SkillsShSource;
+use crate::sync::{MetricUpdate, WriteOp, WriteSender};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+
+/// A backend that skills are synced from: a git repository, a local directory or
+/// archive, an HTTP API or an external command.
+///
+/// Sources only fetch and parse; what they find goes to a [`SourceSink`], which hands
+/// it to the sync writer. Adding a registry backend means implementing this trait and
+/// registering the source in [`sources`].
+#[async_trait]
+pub trait SkillSource: Send + Sync {
+    /// Registry name the source's skills are stored under.
+    fn name(&self) -> &str;
+
+    /// Finds every skill in the source, reads its SKILL.md, and sends the skills to
+    /// `sink`, finishing with [`SourceSink::complete`]. A source that can be interrupted
+    /// saves its progress with [`SourceSink::cursor`] and resumes from `ctx.cursor`.
+    async fn list_skills(&self, ctx: &SourceContext, sink: &SourceSink) -> Result<()>;
+
+    /// Reads one skill's SKILL.md from the source as it is now, without a full listing;
+    /// git and archive sources read their checkout or extraction from the last sync.
+    async fn fetch_skill(&self, _ctx: &SourceContext, slug: &str) -> Result<String> {
+        anyhow::bail!("registry '{}' can't fetch single skills (looking for '{}')", self.name(), slug)
+    }
+
+    /// Fetches popularity metrics for the source's skills; runs alongside `list_skills`.
+    async fn fetch_metrics(&self, _ctx: &SourceContext, _sink: &SourceSink) -> Result<()> {
+        Ok(())
+    }
+
+    /// Describes how far an interrupted sync got from the cursor it saved, for
+    /// `sync status`; `None` if the cursor means nothing to show.
+    fn describe_cursor(&self, _cursor: &str) -> Option<String> {
+        None
+    }
+}
+
+/// What the sync driver gives each source.
+pub struct SourceContext {
+    /// Where sources keep clones and extracted archives.
+    pub repos_dir: PathBuf,
+    pub http: HttpClient,
+    /// Sync-wide limit on concurrent fetches; hold a permit while cloning or downloading.
+    pub limiter: Arc<Semaphore>,
+    /// Progress saved by an interrupted sync of this source.
+    pub cursor: Option<String>,
+}
+
+/// Sends a source's results to the sync writer under its registry name.
+#[derive(Clone)]
+pub struct SourceSink {
+    registry: String,
+    tx: WriteSender,
+}
+
+impl SourceSink {
+    pub fn new(registry: &str, tx: WriteSender) -> Self {
+        Self {
+            registry: registry.to_string(),
+            tx,
+        }
+    }
+
+    /// Upserts skills, and reports the ones that were found but couldn't be read.
+    pub async fn skills(&self, skills: Vec<Skill>, failed: Vec<SkillError>) -> Result<()> {
+        self.send(WriteOp::Skills {
+            registry: self.registry.clone(),
+            skills,
+            failed,
+        })
+        .await
+    }
+
+    /// Records metric values observed at `source` (e.g. an API URL).
+    pub async fn metrics(&self, source: &str, metrics: Vec<MetricUpdate>) -> Result<()> {
+        self.send(WriteOp::Metrics {
+            registry: self.registry.clone(),
+            source: source.to_string(),
+            fetched_at: now()?,
+            metrics,
+        })
+        .await
+    }
+
+    /// Saves (or with `None`, clears) where an interrupted sync should resume.
+    pub async fn cursor(&self, cursor: Option<String>) -> Result<()> {
+        self.send(WriteOp::Cursor {
+            registry: self.registry.clone(),
+            cursor,
+        })
+        .await
+    }
+
+    /// Marks the listing as complete, so skills it didn't send are removed; `commit`
+    /// is the revision a git source was synced at.
+    pub async fn complete(&self, commit: Option<String>) -> Result<()> {
+        self.send(WriteOp::Synced {
+            registry: self.registry.clone(),
+            timestamp: now()?,
+            commit,
+        })
+        .await
+    }
+
+    async fn send(&self, op: WriteOp) -> Result<()> {
+        self.tx
+            .send(op)
+            .await
+            .map_err(|_| anyhow::anyhow!("sync writer closed"))
+    }
+}
+
+fn now() -> Result<i64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+/// Every source to sync: one per configured registry, then the skills.sh catalog.
+pub fn sources(registries: &[Registry]) -> Vec<Arc<dyn SkillSource>> {
+    let mut sources: Vec<Arc<dyn SkillSource>> = registries
+        .iter()
+        .map(|registry| -> Arc<dyn SkillSource> {
+            match registry.kind {
+                RegistryKind::Git if registry.metrics == Some(MetricsApi::Clawdhub) => {
+                    Arc::new(ClawdhubSource::new(GitSource::new(registry.clone())))
+                }
+                RegistryKind::Git => Arc::new(GitSource::new(registry.clone())),
+                RegistryKind::Path | RegistryKind::Archive => Arc::new(LocalSource::new(registry.clone())),
+                RegistryKind::Plugin => Arc::new(PluginSource::new(registry.clone())),
+            }
+        })
+        .collect();
+    sources.push(Arc::new(SkillsShSource));
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github;
+
+    #[test]
+    fn test_sources_cover_every_registry() {
+        let mut registries = github::builtin_registries();
+        registries[4].kind = RegistryKind::Path;
+        let names: Vec<String> = sources(&registries).iter().map(|s| s.name().to_string()).collect();
+        assert_eq!(
+            names,
+            ["clawdhub", "anthropic", "openai", "openai-experimental", "jo", "skillssh"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sink_tags_writes_with_registry() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let sink = SourceSink::new("jo", tx);
+        sink.complete(Some("abc".to_string())).await.unwrap();
+        match rx.recv().await {
+            Some(WriteOp::Synced { registry, commit, .. }) => {
+                assert_eq!(registry, "jo");
+                assert_eq!(commit.as_deref(), Some("abc"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use crate::db::{Database, Skill, UpsertOutcome};
+use crate::github::{self, Registry};
+use crate::http::{CachedResponse, HttpClient};
+use crate::metrics::MetricKind;
+use crate::report::{self, SkillError, SyncReport};
+use crate::source::{self, SourceContext, SourceSink};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+
+/// Default number of registries (and skills.sh source repos) fetched at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub concurrency: usize,
+    /// Registries to sync, usually from `Config::registries`; see `source::sources`.
+    pub registries: Vec<Registry>,
+    /// Names of the sources to sync (including `skillssh`); `None` syncs all of them.
+    pub only: Option<Vec<String>>,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            registries: github::builtin_registries(),
+            only: None,
+        }
+    }
+}
+
+/// A source whose last successful sync is older than its max age, or that has never
+/// synced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleRegistry {
+    pub name: String,
+    pub last_sync: Option<i64>,
+    pub max_age: i64,
+}
+
+impl std::fmt::Display for StaleRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.last_sync {
+            Some(last_sync) => write!(
+                f,
+                "{} (last synced {}, older than {})",
+                self.name,
+                report::format_timestamp(last_sync),
+                format_age(self.max_age)
+            ),
+            None => write!(f, "{} (never synced)", self.name),
+        }
+    }
+}
+
+/// Every source to sync from `registries` whose data is older than its `max_age`,
+/// or `default_max_age` when it has none.
+pub fn stale_registries(
+    db: &Database,
+    registries: &[Registry],
+    default_max_age: i64,
+    now: i64,
+) -> Result<Vec<StaleRegistry>> {
+    let states = db.get_sync_states()?;
+    Ok(source::sources(registries)
+        .iter()
+        .filter_map(|source| {
+            let name = source.name();
+            let max_age = registries
+                .iter()
+                .find(|r| r.name == name)
+                .and_then(|r| r.max_age)
+                .unwrap_or(default_max_age);
+            let last_sync = states.iter().find(|s| s.registry == name).and_then(|s| s.last_sync);
+            match last_sync {
+                Some(t) if now - t <= max_age => None,
+                _ => Some(StaleRegistry {
+                    name: name.to_string(),
+                    last_sync,
+                    max_age,
+                }),
+            }
+        })
+        .collect())
+}
+
+/// Renders an age in seconds in its largest whole unit, as `max_age` would be written.
+pub fn format_age(seconds: i64) -> String {
+    match seconds {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s => format!("{}m", s / 60),
+    }
+}
+
+/// A metric value observed for one skill.
+#[derive(Debug, Clone)]
+pub struct MetricUpdate {
+    pub slug: String,
+    pub kind: MetricKind,
+    pub value: i64,
+}
+
+/// Database writes produced by sync tasks. Tasks only fetch and parse; every write
+/// goes through a channel (wrapped in a `SourceSink`) to the single writer in
+/// `sync_all`, so SQLite never sees concurrent writers.
+#[derive(Debug)]
+pub enum WriteOp {
+    /// Upserts every skill found in a registry, and reports the ones that failed to parse.
+    Skills {
+        registry: String,
+        skills: Vec<Skill>,
+        failed: Vec<SkillError>,
+    },
+    /// Records popularity metrics for skills of a registry.
+    Metrics {
+        registry: String,
+        source: String,
+        fetched_at: i64,
+        metrics: Vec<MetricUpdate>,
+    },
+    /// Saves (or clears) a resume point for an interrupted sync.
+    Cursor { registry: String, cursor: Option<String> },
+    /// Marks a registry as completely synced at `commit` (for git registries); its
+    /// skills not seen in this run are removed.
+    Synced {
+        registry: String,
+        timestamp: i64,
+        commit: Option<String>,
+    },
+    /// Stores an API response's `ETag` for conditional requests on the next sync.
+    HttpCache { url: String, response: CachedResponse },
+}
+
+pub type WriteSender = mpsc::Sender<WriteOp>;
+
+struct Writer<'a> {
+    db: &'a mut Database,
+    /// Skill and metric writes of each registry, held until the registry completes so
+    /// they are committed in one transaction.
+    pending: BTreeMap<String, Vec<WriteOp>>,
+    report: SyncReport,
+}
+
+impl<'a> Writer<'a> {
+    fn new(db: &'a mut Database) -> Self {
+        Self {
+            db,
+            pending: BTreeMap::new(),
+            report: SyncReport::default(),
+        }
+    }
+
+    fn apply(&mut self, op: WriteOp) -> Result<()> {
+        match op {
+            WriteOp::Skills { ref registry, .. } | WriteOp::Metrics { ref registry, .. } => {
+                let registry = registry.clone();
+                self.pending.entry(registry).or_default().push(op);
+            }
+            WriteOp::Synced {
+                registry,
+                timestamp,
+                commit,
+            } => {
+                let ops = self.pending.remove(&registry).unwrap_or_default();
+                self.commit(&registry, ops, Some((timestamp, commit)));
+            }
+            WriteOp::Cursor { registry, cursor } => {
+                self.db.set_sync_cursor(&registry, cursor.as_deref())?;
+            }
+            WriteOp::HttpCache { url, response } => {
+                self.db.set_http_cache(&url, &response)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a registry's skills and metrics in one transaction and, when `synced`
+    /// marks its listing complete, removes the skills it no longer has. If the
+    /// transaction fails, nothing of it is written and the error goes in the report.
+    fn commit(&mut self, registry: &str, ops: Vec<WriteOp>, synced: Option<(i64, Option<String>)>) {
+        let entry = self.report.registry_mut(registry);
+        let before = entry.clone();
+        let result = self.db.in_transaction(|db| {
+            // Skills first, so metrics that arrived before them find their rows
+            let (listings, metrics): (Vec<_>, Vec<_>) =
+                ops.into_iter().partition(|op| matches!(op, WriteOp::Skills { .. }));
+            let listed = !listings.is_empty();
+            let mut seen: HashSet<String> = HashSet::new();
+            for op in listings.into_iter().chain(metrics) {
+                match op {
+                    WriteOp::Skills { skills, failed, .. } => {
+                        for skill in &skills {
+                            match db.upsert_skill_tracked(skill)? {
+                                UpsertOutcome::Inserted => entry.new.push(skill.slug.clone()),
+                                UpsertOutcome::Updated => entry.updated.push(skill.slug.clone()),
+                                UpsertOutcome::Unchanged => entry.unchanged += 1,
+                            }
+                            seen.insert(skill.slug.clone());
+                        }
+                        // A skill that failed to parse this time is not gone from the registry
+                        seen.extend(failed.iter().map(|f| f.slug.clone()));
+                        entry.failed.extend(failed);
+                    }
+                    WriteOp::Metrics {
+                        source,
+                        fetched_at,
+                        metrics,
+                        ..
+                    } => {
+                        for m in &metrics {
+                            if m.kind == MetricKind::Stars {
+                                db.update_stars(registry, &m.slug, m.value)?;
+                            }
+                            db.set_metric(registry, &m.slug, m.kind, m.value, &source, fetched_at)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if let Some((timestamp, commit)) = synced {
+                if listed {
+                    entry.removed.extend(db.remove_skills_except(registry, &seen)?);
+                }
+                db.set_last_sync(registry, timestamp, None)?;
+                db.set_sync_commit(registry, commit.as_deref())?;
+                entry.commit = commit;
+            }
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to write sync results for {}: {:#}", registry, e);
+            *entry = before;
+            entry.add_error(format!("writing results failed: {:#}", e));
+        }
+    }
+
+    /// Commits what registries that never completed did send: skills of a failed or
+    /// interrupted listing (without removing anything), and metrics for the skills
+    /// already in the database (e.g. after a failed clone).
+    fn finish(mut self) -> Result<SyncReport> {
+        for (registry, ops) in std::mem::take(&mut self.pending) {
+            self.commit(&registry, ops, None);
+        }
+        Ok(self.report)
+    }
+}
+
+/// Syncs every registry concurrently, with at most `options.concurrency` fetches in
+/// flight, and applies their results through a single database writer.
+///
+/// Returns a report of what changed; a registry that fails is recorded in the report
+/// rather than failing the whole sync.
+pub async fn sync_all(db: &mut Database, repos_dir: &Path, options: &SyncOptions) -> Result<SyncReport> {
+    std::fs::create_dir_all(repos_dir)?;
+    let started_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let started = Instant::now();
+
+    let (tx, mut rx) = mpsc::channel::<WriteOp>(64);
+    let limiter = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let mut tasks: JoinSet<(String, Result<()>, Duration)> = JoinSet::new();
+
+    let http = HttpClient::new(db.get_http_cache()?, Some(tx.clone()))?;
+    let wanted = |name: &str| options.only.as_ref().is_none_or(|only| only.iter().any(|n| n == name));
+    for source in source::sources(&options.registries).into_iter().filter(|s| wanted(s.name())) {
+        let name = source.name().to_string();
+        let ctx = Arc::new(SourceContext {
+            repos_dir: repos_dir.to_path_buf(),
+            http: http.clone(),
+            limiter: limiter.clone(),
+            cursor: db.get_sync_cursor(&name)?,
+        });
+        let sink = SourceSink::new(&name, tx.clone());
+
+        {
+            let (source, ctx, sink, name) = (source.clone(), ctx.clone(), sink.clone(), name.clone());
+            tasks.spawn(async move {
+                let start = Instant::now();
+                tracing::info!("Syncing registry: {}", name);
+                let result = source.list_skills(&ctx, &sink).await;
+                (name, result, start.elapsed())
+            });
+        }
+        tasks.spawn(async move {
+            let start = Instant::now();
+            let result = source.fetch_metrics(&ctx, &sink).await.context("fetching metrics");
+            (name, result, start.elapsed())
+        });
+    }
+    // The writer stops once every task (and the HTTP client's cache) has dropped its sender
+    drop((tx, http));
+
+    let mut writer = Writer::new(db);
+    while let Some(op) = rx.recv().await {
+        if let Err(e) = writer.apply(op) {
+            tracing::warn!("Failed to write sync results: {}", e);
+        }
+    }
+    let mut report = writer.finish()?;
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((registry, result, elapsed)) => {
+                let entry = report.registry_mut(&registry);
+                entry.duration_ms = entry.duration_ms.max(elapsed.as_millis() as u64);
+                if let Err(e) = result {
+                    tracing::warn!("Failed to sync {}: {:#}", registry, e);
+                    entry.add_error(format!("{:#}", e));
+                }
+            }
+            Err(e) => tracing::warn!("Sync task panicked: {}", e),
+        }
+    }
+    for entry in &report.registries {
+        db.set_sync_attempt(&entry.registry, started_at, entry.error.as_deref())?;
+    }
+
+    report.started_at = started_at;
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_skill(slug: &str, registry: &str) -> Skill {
+        Skill {
+            id: 0,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            registry: registry.to_string(),
+            description: String::new(),
+            skill_md: format!("# {}", slug),
+            github_url: format!("https://github.com/test/{}", slug),
+            version: None,
+            stars: 0,
+            trusted: false,
+            updated_at: 1234567890,
+            commit: None,
+            permalink: None,
+        }
+    }
+
+    fn stars(slug: &str, value: i64) -> WriteOp {
+        WriteOp::Metrics {
+            registry: "clawdhub".to_string(),
+            source: "test".to_string(),
+            fetched_at: 1,
+            metrics: vec![MetricUpdate {
+                slug: slug.to_string(),
+                kind: MetricKind::Stars,
+                value,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_writer_defers_metrics_until_skills_arrive() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+
+        let mut writer = Writer::new(&mut db);
+        writer.apply(stars("calendar", 7)).unwrap();
+        writer
+            .apply(WriteOp::Skills {
+                registry: "clawdhub".to_string(),
+                skills: vec![create_test_skill("calendar", "clawdhub")],
+                failed: Vec::new(),
+            })
+            .unwrap();
+        let report = writer.finish().unwrap();
+        assert_eq!(report.registries[0].new, vec!["calendar"]);
+
+        assert_eq!(db.get_skill("clawdhub", "calendar").unwrap().unwrap().stars, 7);
+    }
+
+    #[test]
+    fn test_writer_applies_orphan_metrics_on_finish() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("calendar", "clawdhub")).unwrap();
+
+        let mut writer = Writer::new(&mut db);
+        writer.apply(stars("calendar", 3)).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(db.get_skill("clawdhub", "calendar").unwrap().unwrap().stars, 3);
+    }
+
+    #[test]
+    fn test_writer_counts_outcomes() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("existing", "anthropic")).unwrap();
+
+        let mut writer = Writer::new(&mut db);
+        writer
+            .apply(WriteOp::Skills {
+                registry: "anthropic".to_string(),
+                skills: vec![create_test_skill("existing", "anthropic"), create_test_skill("fresh", "anthropic")],
+                failed: Vec::new(),
+            })
+            .unwrap();
+        let report = writer.finish().unwrap();
+        assert_eq!(report.registries[0].new, vec!["fresh"]);
+        assert_eq!(report.registries[0].unchanged, 1);
+    }
+
+    #[test]
+    fn test_writer_removes_skills_missing_from_a_complete_sync() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+        for slug in ["kept", "broken", "deleted"] {
+            db.upsert_skill(&create_test_skill(slug, "anthropic")).unwrap();
+        }
+        db.upsert_skill(&create_test_skill("deleted", "jo")).unwrap();
+
+        let mut writer = Writer::new(&mut db);
+        writer
+            .apply(WriteOp::Skills {
+                registry: "anthropic".to_string(),
+                skills: vec![create_test_skill("kept", "anthropic")],
+                failed: vec![SkillError {
+                    slug: "broken".to_string(),
+                    error: "invalid frontmatter".to_string(),
+                }],
+            })
+            .unwrap();
+        writer
+            .apply(WriteOp::Synced {
+                registry: "anthropic".to_string(),
+                timestamp: 1,
+                commit: Some("abc123".to_string()),
+            })
+            .unwrap();
+        let report = writer.finish().unwrap();
+        assert_eq!(report.registries[0].commit.as_deref(), Some("abc123"));
+
+        assert_eq!(report.registries[0].removed, vec!["deleted"]);
+        assert_eq!(report.registries[0].failed.len(), 1);
+        assert!(db.get_skill("anthropic", "deleted").unwrap().is_none());
+        assert!(db.get_skill("anthropic", "broken").unwrap().is_some());
+        assert!(db.get_skill("jo", "deleted").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_writer_rolls_back_a_failed_registry() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = Database::open(&path).unwrap();
+        db.upsert_skill(&create_test_skill("deleted", "anthropic")).unwrap();
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TRIGGER fail BEFORE DELETE ON skills BEGIN SELECT RAISE(ABORT, 'disk full'); END;")
+            .unwrap();
+
+        let mut writer = Writer::new(&mut db);
+        writer
+            .apply(WriteOp::Skills {
+                registry: "anthropic".to_string(),
+                skills: vec![create_test_skill("fresh", "anthropic")],
+                failed: Vec::new(),
+            })
+            .unwrap();
+        writer
+            .apply(WriteOp::Synced {
+                registry: "anthropic".to_string(),
+                timestamp: 1,
+                commit: Some("abc123".to_string()),
+            })
+            .unwrap();
+        let report = writer.finish().unwrap();
+
+        let entry = &report.registries[0];
+        assert!(entry.error.as_deref().unwrap().contains("disk full"));
+        assert!(entry.new.is_empty());
+        assert!(entry.commit.is_none());
+        assert!(db.get_skill("anthropic", "fresh").unwrap().is_none());
+        assert!(db.get_skill("anthropic", "deleted").unwrap().is_some());
+        assert!(db.get_sync_states().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stale_registries() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        let mut registries = github::builtin_registries();
+        registries.truncate(2);
+        registries[1].max_age = Some(3600);
+        let (fresh, strict) = (registries[0].name.clone(), registries[1].name.clone());
+
+        let now = 10 * 86400;
+        db.set_last_sync(&fresh, now - 86400, None).unwrap();
+        db.set_last_sync(&strict, now - 7200, None).unwrap();
+
+        let stale = stale_registries(&db, &registries, 2 * 86400, now).unwrap();
+        let names: Vec<&str> = stale.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, [strict.as_str(), "skillssh"]);
+        assert_eq!(stale[0].max_age, 3600);
+        assert!(stale[0].to_string().ends_with("older than 1h)"));
+        assert_eq!(stale[1].to_string(), "skillssh (never synced)");
+
+        assert_eq!(stale_registries(&db, &registries, 2 * 86400, now - 3600).unwrap().len(), 1);
+        db.set_last_sync("skillssh", now, None).unwrap();
+        assert_eq!(stale_registries(&db, &registries, 86400, now - 3600).unwrap(), Vec::new());
+    }
+
+    #[tokio::test]
+    async fn test_sync_all_records_attempts() {
+        let dir = tempdir().unwrap();
+        let shared = dir.path().join("shared/pdf");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(shared.join("SKILL.md"), "---\nname: pdf\ndescription: PDF tools\n---\n# PDF").unwrap();
+        let path_registry = |name: &str, path: &Path| Registry {
+            name: name.to_string(),
+            kind: github::RegistryKind::Path,
+            repo_url: String::new(),
+            path: Some(path.display().to_string()),
+            skills_path: String::new(),
+            trusted: false,
+            forge: None,
+            git_ref: None,
+            command: None,
+            args: Vec::new(),
+            timeout_secs: None,
+            max_age: None,
+            metrics: None,
+        };
+        let options = SyncOptions {
+            registries: vec![
+                path_registry("shared", &dir.path().join("shared")),
+                path_registry("gone", &dir.path().join("gone")),
+            ],
+            only: Some(vec!["shared".to_string(), "gone".to_string()]),
+            ..Default::default()
+        };
+
+        let mut db = Database::open(&dir.path().join("test.db")).unwrap();
+        let report = sync_all(&mut db, &dir.path().join("repos"), &options).await.unwrap();
+        assert_eq!(report.registries.len(), 2);
+
+        let states = db.get_sync_states().unwrap();
+        let gone = states.iter().find(|s| s.registry == "gone").unwrap();
+        assert_eq!(gone.last_sync, None);
+        assert_eq!(gone.last_attempt, Some(report.started_at));
+        assert!(gone.last_error.is_some());
+        let shared = states.iter().find(|s| s.registry == "shared").unwrap();
+        assert!(shared.last_sync.is_some());
+        assert_eq!(shared.last_attempt, Some(report.started_at));
+        assert_eq!(shared.last_error, None);
+        assert_eq!(db.count_skills_by_registry().unwrap()["shared"], 1);
+    }
+}
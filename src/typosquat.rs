@@ -0,0 +1,226 @@
+use crate::db::{Database, Finding, Skill};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const FINDING_KIND: &str = "impersonation";
+
+/// Words an impersonator adds to borrow a trusted registry's credibility.
+const BRAND_WORDS: &[&str] = &["official", "verified", "claude", "anthropic", "openai", "chatgpt", "gpt", "jo"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suspicion {
+    pub severity: &'static str,
+    pub reason: String,
+}
+
+/// Compares every untrusted skill against the trusted ones and records a finding for
+/// each suspected impersonator. Returns the number of skills flagged.
+pub fn detect_impersonators(db: &Database) -> Result<usize> {
+    let skills = db.get_all_skills()?;
+    let (trusted, untrusted): (Vec<&Skill>, Vec<&Skill>) = skills.iter().partition(|s| s.trusted);
+    let fingerprints: HashMap<i64, _> = db.get_fingerprints()?.into_iter().map(|f| (f.id, f)).collect();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+    // Verbatim copies of a trusted skill are duplicates, not impersonators
+    let is_copy = |candidate: i64, target: i64| match (fingerprints.get(&candidate), fingerprints.get(&target)) {
+        (Some(c), Some(t)) => {
+            c.canonical_id == Some(target) || (c.content_hash.is_some() && c.content_hash == t.content_hash)
+        }
+        _ => false,
+    };
+
+    let mut findings = Vec::new();
+    for candidate in &untrusted {
+        for target in &trusted {
+            if is_copy(candidate.id, target.id) {
+                continue;
+            }
+            let suspicion = check_name(&candidate.slug, &target.slug)
+                .or_else(|| check_name(&candidate.name, &target.name));
+            if let Some(s) = suspicion {
+                findings.push(Finding {
+                    skill_id: candidate.id,
+                    kind: FINDING_KIND.to_string(),
+                    severity: s.severity.to_string(),
+                    detail: format!("possible impersonation of {}:{} ({})", target.registry, target.slug, s.reason),
+                    created_at: now,
+                });
+                break;
+            }
+        }
+    }
+
+    db.replace_findings(FINDING_KIND, &findings)?;
+    if !findings.is_empty() {
+        tracing::warn!("Flagged {} skills as possible impersonators of trusted skills", findings.len());
+    }
+    Ok(findings.len())
+}
+
+/// Checks whether `candidate` looks like an imitation of the trusted name `target`.
+pub fn check_name(candidate: &str, target: &str) -> Option<Suspicion> {
+    let c = skeleton(candidate);
+    let t = skeleton(target);
+    if t.len() < 3 || c.is_empty() {
+        return None;
+    }
+
+    if candidate.eq_ignore_ascii_case(target) {
+        return Some(Suspicion {
+            severity: "medium",
+            reason: "same name as a trusted skill".to_string(),
+        });
+    }
+    if c == t {
+        return Some(Suspicion {
+            severity: "high",
+            reason: "visually identical name".to_string(),
+        });
+    }
+    if strip_brand_words(candidate) == t {
+        return Some(Suspicion {
+            severity: "high",
+            reason: "trusted name with a brand prefix or suffix".to_string(),
+        });
+    }
+
+    let max_distance = if t.len() >= 8 { 2 } else { 1 };
+    let distance = damerau_levenshtein(&c, &t);
+    if distance <= max_distance {
+        return Some(Suspicion {
+            severity: "medium",
+            reason: format!("name within edit distance {} of a trusted skill", distance),
+        });
+    }
+    None
+}
+
+/// Lowercases, folds common homoglyphs to ASCII and drops separators, so that
+/// `Pdf`, `p-d-f` and `рdf` (Cyrillic `р`) all map to `pdf`.
+pub fn skeleton(name: &str) -> String {
+    let folded: String = name
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            '-' | '_' | '.' | ' ' => None,
+            'а' | 'α' => Some('a'),
+            'с' | 'ϲ' => Some('c'),
+            'е' | 'ε' | '3' => Some('e'),
+            'һ' => Some('h'),
+            'і' | 'ι' | '!' => Some('i'),
+            'ј' => Some('j'),
+            'к' | 'κ' => Some('k'),
+            '1' | '|' | 'ӏ' => Some('l'),
+            'о' | 'ο' | '0' => Some('o'),
+            'р' | 'ρ' => Some('p'),
+            'ѕ' | '5' | '$' => Some('s'),
+            'т' | 'τ' => Some('t'),
+            'υ' => Some('u'),
+            'ν' => Some('v'),
+            'х' | 'χ' => Some('x'),
+            'у' | 'γ' => Some('y'),
+            c => Some(c),
+        })
+        .collect();
+    folded.replace("rn", "m").replace("vv", "w")
+}
+
+fn strip_brand_words(name: &str) -> String {
+    let lowered = name.to_lowercase();
+    let parts: Vec<&str> = lowered
+        .split(['-', '_', '.', ' '])
+        .filter(|p| !p.is_empty() && !BRAND_WORDS.contains(p))
+        .collect();
+    skeleton(&parts.join("-"))
+}
+
+/// Optimal string alignment distance (edits plus adjacent transpositions).
+fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_skill(slug: &str, registry: &str, trusted: bool, skill_md: &str) -> Skill {
+        Skill {
+            id: 0,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            registry: registry.to_string(),
+            description: String::new(),
+            skill_md: skill_md.to_string(),
+            github_url: format!("https://github.com/test/{}", slug),
+            version: None,
+            stars: 0,
+            trusted,
+            updated_at: 1234567890,
+            commit: None,
+            permalink: None,
+        }
+    }
+
+    #[test]
+    fn test_check_name_flags_impersonation() {
+        assert_eq!(check_name("pfd", "pdf").unwrap().severity, "medium");
+        assert_eq!(check_name("anthropic-pdf", "pdf").unwrap().severity, "high");
+        assert_eq!(check_name("pdf-official", "pdf").unwrap().severity, "high");
+        assert_eq!(check_name("р-d-f", "pdf").unwrap().severity, "high");
+        assert_eq!(check_name("docx", "docx").unwrap().severity, "medium");
+        assert!(check_name("spreadsheet", "pdf").is_none());
+        assert!(check_name("xlsx", "pptx").is_none());
+    }
+
+    #[test]
+    fn test_skeleton_folds_homoglyphs() {
+        assert_eq!(skeleton("Mod-El"), "model");
+        assert_eq!(skeleton("rnodel"), "model");
+        assert_eq!(skeleton("w0rd"), "word");
+    }
+
+    #[test]
+    fn test_damerau_levenshtein() {
+        assert_eq!(damerau_levenshtein("pdf", "pfd"), 1);
+        assert_eq!(damerau_levenshtein("pdf", "pdfs"), 1);
+        assert_eq!(damerau_levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_detect_impersonators_records_findings() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        db.upsert_skill(&create_test_skill("pdf", "anthropic", true, "# PDF")).unwrap();
+        let squat = db.upsert_skill(&create_test_skill("anthropic-pdf", "clawdhub", false, "# Evil")).unwrap();
+        let benign = db.upsert_skill(&create_test_skill("calendar", "clawdhub", false, "# Calendar")).unwrap();
+
+        assert_eq!(detect_impersonators(&db).unwrap(), 1);
+
+        let findings = db.get_findings(squat).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].detail.contains("anthropic:pdf"));
+        assert!(db.get_findings(benign).unwrap().is_empty());
+
+        // Re-running replaces rather than accumulates findings
+        detect_impersonators(&db).unwrap();
+        assert_eq!(db.get_findings(squat).unwrap().len(), 1);
+    }
+}